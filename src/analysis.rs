@@ -0,0 +1,360 @@
+//! static analysis of rom images, without running them
+
+use crate::emulator::core::START_ADDR;
+use crate::emulator::opcodes;
+use crate::emulator::quirks::Quirks;
+use crate::emulator::variant::Variant;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// opcode pattern an instruction belongs to, e.g. `0x6A12` -> `"6XNN"`.
+/// covers chip8 plus the schip, xo-chip and chip-8x extensions, anything else is `"????"`
+pub fn opcode_family(op: u16) -> &'static str {
+    let nib1 = (op & 0xF000) >> 12;
+    let nib2 = (op & 0x0F00) >> 8;
+    let nib3 = (op & 0x00F0) >> 4;
+    let nib4 = op & 0x000F;
+
+    match (nib1, nib2, nib3, nib4) {
+        (0x0, 0x0, 0xE, 0x0) => "00E0",
+        (0x0, 0x0, 0xE, 0xE) => "00EE",
+        (0x0, 0x0, 0xC, _) => "00CN",
+        (0x0, 0x0, 0xD, _) => "00DN",
+        (0x0, 0x0, 0xF, 0xB) => "00FB",
+        (0x0, 0x0, 0xF, 0xC) => "00FC",
+        (0x0, 0x0, 0xF, 0xD) => "00FD",
+        (0x0, 0x0, 0xF, 0xE) => "00FE",
+        (0x0, 0x0, 0xF, 0xF) => "00FF",
+        (0x0, 0x2, 0xA, 0x0) => "02A0",
+        (0x0, _, _, _) => "0NNN",
+        (0x1, _, _, _) => "1NNN",
+        (0x2, _, _, _) => "2NNN",
+        (0x3, _, _, _) => "3XNN",
+        (0x4, _, _, _) => "4XNN",
+        (0x5, _, _, 0x0) => "5XY0",
+        (0x5, _, _, 0x1) => "5XY1",
+        (0x5, _, _, 0x2) => "5XY2",
+        (0x5, _, _, 0x3) => "5XY3",
+        (0x6, _, _, _) => "6XNN",
+        (0x7, _, _, _) => "7XNN",
+        (0x8, _, _, 0x0) => "8XY0",
+        (0x8, _, _, 0x1) => "8XY1",
+        (0x8, _, _, 0x2) => "8XY2",
+        (0x8, _, _, 0x3) => "8XY3",
+        (0x8, _, _, 0x4) => "8XY4",
+        (0x8, _, _, 0x5) => "8XY5",
+        (0x8, _, _, 0x6) => "8XY6",
+        (0x8, _, _, 0x7) => "8XY7",
+        (0x8, _, _, 0xE) => "8XYE",
+        (0x9, _, _, 0x0) => "9XY0",
+        (0xA, _, _, _) => "ANNN",
+        (0xB, _, _, _) => "BNNN",
+        (0xC, _, _, _) => "CXNN",
+        (0xD, _, _, 0x0) => "DXY0",
+        (0xD, _, _, _) => "DXYN",
+        (0xE, _, 0x9, 0xE) => "EX9E",
+        (0xE, _, 0xA, 0x1) => "EXA1",
+        (0xE, _, 0xF, 0x2) => "EXF2",
+        (0xE, _, 0xF, 0x5) => "EXF5",
+        (0xF, 0x0, 0x0, 0x0) => "F000",
+        (0xF, _, 0x0, 0x1) => "FN01",
+        (0xF, 0x0, 0x0, 0x2) => "F002",
+        (0xF, _, 0x0, 0x7) => "FX07",
+        (0xF, _, 0x0, 0xA) => "FX0A",
+        (0xF, _, 0x1, 0x5) => "FX15",
+        (0xF, _, 0x1, 0x8) => "FX18",
+        (0xF, _, 0x1, 0xE) => "FX1E",
+        (0xF, _, 0x2, 0x9) => "FX29",
+        (0xF, _, 0x3, 0x0) => "FX30",
+        (0xF, _, 0x3, 0x3) => "FX33",
+        (0xF, _, 0x3, 0xA) => "FX3A",
+        (0xF, _, 0x5, 0x5) => "FX55",
+        (0xF, _, 0x6, 0x5) => "FX65",
+        (0xF, _, 0x7, 0x5) => "FX75",
+        (0xF, _, 0x8, 0x5) => "FX85",
+        (0xF, _, 0xF, 0x8) => "FXF8",
+        (0xF, _, 0xF, 0xB) => "FXFB",
+        (_, _, _, _) => "????",
+    }
+}
+
+/// big endian words of a rom, in the order they'd be fetched when the rom
+/// is loaded on an even address. data and odd-aligned code are included too,
+/// so everything built on this is a heuristic
+pub fn words(rom: &[u8]) -> impl Iterator<Item = u16> + '_ {
+    rom.chunks(2)
+        .map(|pair| u16::from(pair[0]) << 8 | u16::from(pair.get(1).copied().unwrap_or(0)))
+}
+
+/// how often each opcode family appears in the rom
+pub fn opcode_histogram(rom: &[u8]) -> BTreeMap<&'static str, usize> {
+    let mut histogram = BTreeMap::new();
+    for op in words(rom) {
+        *histogram.entry(opcode_family(op)).or_insert(0) += 1;
+    }
+    histogram
+}
+
+const SCHIP_ONLY: &[&str] = &[
+    "00CN", "00FB", "00FC", "00FD", "00FE", "00FF", "DXY0", "FX30", "FX75", "FX85",
+];
+const XO_CHIP_ONLY: &[&str] = &["00DN", "5XY2", "5XY3", "F000", "FN01", "F002", "FX3A"];
+// chip-8x's `5XY1` is left out, chip-8e uses the same encoding
+const CHIP_8X_ONLY: &[&str] = &["02A0", "EXF2", "EXF5", "FXF8", "FXFB"];
+
+/// a single stray match is usually sprite data that happens to decode as an
+/// extension opcode, so a variant needs at least this many hits to be picked
+const MIN_HITS: usize = 2;
+
+/// result of scanning a rom for extension-only opcodes
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VariantReport {
+    pub schip_hits: usize,
+    pub xo_chip_hits: usize,
+    pub chip_8x_hits: usize,
+    /// always a variant the interpreter implements
+    pub recommended: Variant,
+    /// the behaviour roms for `recommended` usually expect, `Quirks::for_variant`
+    pub quirks: Quirks,
+    /// a dialect the rom looks written for that the interpreter has no opcodes for,
+    /// it will likely fault when run as `recommended`
+    pub unsupported: Option<Variant>,
+}
+
+/// guess which interpreter a rom was written for by looking for opcodes that
+/// only exist in one of the extensions. xo-chip is a superset of schip, so
+/// it wins whenever its own opcodes show up. chip-8x isn't implemented, so it is
+/// only reported as `unsupported`
+pub fn detect_variant(rom: &[u8]) -> VariantReport {
+    let histogram = opcode_histogram(rom);
+    let hits = |families: &[&str]| -> usize {
+        families
+            .iter()
+            .map(|family| histogram.get(family).copied().unwrap_or(0))
+            .sum()
+    };
+
+    let mut report = VariantReport {
+        schip_hits: hits(SCHIP_ONLY),
+        xo_chip_hits: hits(XO_CHIP_ONLY),
+        chip_8x_hits: hits(CHIP_8X_ONLY),
+        recommended: Variant::Chip8,
+        quirks: Quirks::default(),
+        unsupported: None,
+    };
+
+    report.recommended = if report.xo_chip_hits >= MIN_HITS {
+        Variant::XoChip
+    } else if report.schip_hits >= MIN_HITS {
+        Variant::SuperChip
+    } else {
+        Variant::Chip8
+    };
+    report.quirks = Quirks::for_variant(report.recommended);
+    if report.recommended == Variant::Chip8 && report.chip_8x_hits >= MIN_HITS {
+        report.unsupported = Some(Variant::Chip8X);
+    }
+    report
+}
+
+/// names of the quirks that can affect some opcode in the rom when run as
+/// `variant`, see `Quirks::NAMES`. data decoding as opcodes can add extra ones
+pub fn quirks_used(rom: &[u8], variant: Variant) -> BTreeSet<&'static str> {
+    words(rom)
+        .filter_map(|op| opcodes::lookup(op, variant))
+        .flat_map(|info| info.quirks.iter().copied())
+        .collect()
+}
+
+/// a sprite a reachable `DXYN`/`DXY0` draws, found by following `ANNN` loads
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SpriteRef {
+    /// address in memory, the rom starts at `START_ADDR`
+    pub addr: usize,
+    /// 8, or 16 for `DXY0`
+    pub width: usize,
+    pub height: usize,
+}
+
+impl SpriteRef {
+    pub fn len(&self) -> usize {
+        self.width / 8 * self.height
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// the result of following control flow from the entry point without running the rom
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Reachability {
+    /// addresses of instructions that can execute
+    pub code: BTreeSet<usize>,
+    /// sprites drawn from an address I was set to on the way there
+    pub sprites: BTreeSet<SpriteRef>,
+    /// keys tested by `EX9E`/`EXA1` with VX set to a constant on the way there
+    pub keys: BTreeSet<u8>,
+    /// `EX9E`/`EXA1` tests of a key that couldn't be worked out
+    pub unknown_key_tests: usize,
+    /// whether an `FX0A` can execute, which accepts any key
+    pub waits_for_key: bool,
+}
+
+/// follow jumps, calls and skips from `START_ADDR`. computed jumps (`BNNN`) and
+/// anything depending on register values are not followed, so this finds a
+/// subset of the real code. registers are only known along the first path
+/// reaching an instruction, so the keys are a subset too
+pub fn reachability(rom: &[u8], variant: Variant) -> Reachability {
+    let end = START_ADDR + rom.len();
+    let word_at = |addr: usize| -> Option<u16> {
+        let offset = addr.checked_sub(START_ADDR)?;
+        let hi = *rom.get(offset)?;
+        Some(u16::from(hi) << 8 | u16::from(rom.get(offset + 1).copied().unwrap_or(0)))
+    };
+
+    let mut result = Reachability::default();
+    // address and the values of I and V0..VF when getting there, where they are known
+    let mut pending = vec![(START_ADDR, None::<usize>, [None::<u8>; 16])];
+    while let Some((mut addr, mut i_reg, mut regs)) = pending.pop() {
+        while addr < end && result.code.insert(addr) {
+            let Some(op) = word_at(addr) else { break };
+            let nnn = usize::from(op & 0xFFF);
+            let (x, y) = (usize::from(op >> 8 & 0xF), usize::from(op >> 4 & 0xF));
+            let nn = (op & 0xFF) as u8;
+            let next = addr + 2;
+            let pattern = opcodes::lookup(op, variant).map(|info| info.pattern);
+            match pattern {
+                Some("EX9E" | "EXA1") => match regs[x] {
+                    Some(key) => {
+                        result.keys.insert(key & 0xF);
+                    }
+                    None => result.unknown_key_tests += 1,
+                },
+                Some("FX0A") => result.waits_for_key = true,
+                _ => {}
+            }
+            update_known_regs(&mut regs, pattern, x, y, nn);
+
+            let follow = match pattern {
+                Some("00EE" | "00FD" | "00ED" | "BNNN") | None => None,
+                Some("1NNN") => Some(nnn),
+                Some("2NNN") => {
+                    pending.push((nnn, i_reg, regs));
+                    Some(next)
+                }
+                Some("BBNN") => addr.checked_sub(usize::from(nn)),
+                Some("BFNN") => Some(addr + usize::from(nn)),
+                Some("3XNN" | "4XNN" | "5XY0" | "9XY0" | "5XY1" | "EX9E" | "EXA1" | "0188") => {
+                    // xo-chip's `F000 NNNN` is skipped as a whole
+                    let skipped = word_at(next).and_then(|op| opcodes::lookup(op, variant));
+                    let len = match skipped.map(|info| info.pattern) {
+                        Some("F000") => 4,
+                        _ => 2,
+                    };
+                    pending.push((next + len, i_reg, regs));
+                    Some(next)
+                }
+                Some("ANNN") => {
+                    i_reg = Some(nnn);
+                    Some(next)
+                }
+                Some("F000") => {
+                    i_reg = word_at(next).map(usize::from);
+                    Some(next + 2)
+                }
+                Some(pattern @ ("DXYN" | "DXY0")) => {
+                    if let Some(sprite_addr) = i_reg {
+                        let (width, height) = match pattern {
+                            "DXY0" => (16, 16),
+                            _ => (8, usize::from(op & 0xF)),
+                        };
+                        result.sprites.insert(SpriteRef {
+                            addr: sprite_addr,
+                            width,
+                            height,
+                        });
+                    }
+                    Some(next)
+                }
+                Some("FX1E" | "FX29" | "FX30" | "FX55" | "FX65") => {
+                    // I moves by a register value or the load/store quirk
+                    i_reg = None;
+                    Some(next)
+                }
+                Some(_) => Some(next),
+            };
+            match follow {
+                Some(target) => addr = target,
+                None => break,
+            }
+        }
+    }
+    result
+}
+
+/// track register constants across the instruction `pattern`, forgetting what it
+/// may overwrite with a value that isn't known statically
+fn update_known_regs(
+    regs: &mut [Option<u8>; 16],
+    pattern: Option<&str>,
+    x: usize,
+    y: usize,
+    nn: u8,
+) {
+    match pattern {
+        Some("6XNN") => regs[x] = Some(nn),
+        Some("7XNN") => regs[x] = regs[x].map(|val| val.wrapping_add(nn)),
+        Some("8XY0") => regs[x] = regs[y],
+        Some(pattern) if pattern.starts_with('8') => {
+            regs[x] = None;
+            regs[0xF] = None;
+        }
+        Some("CXNN" | "FX07" | "FX0A") => regs[x] = None,
+        Some("DXYN" | "DXY0" | "FX1E") => regs[0xF] = None,
+        Some("FX65" | "FX85") => regs[..=x].fill(None),
+        Some("5XY3") => regs[x.min(y)..=x.max(y)].fill(None),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(rom: &[u8], variant: Variant) -> Vec<usize> {
+        reachability(rom, variant).code.into_iter().collect()
+    }
+
+    #[test]
+    fn detects_the_variant_and_its_quirks() {
+        // 00FF 00FE twice
+        let report = detect_variant(&[0x00, 0xFF, 0x00, 0xFE, 0x00, 0xFF, 0x00, 0xFE]);
+        assert_eq!(report.recommended, Variant::SuperChip);
+        assert_eq!(report.quirks, Quirks::schip());
+
+        // chip-8e's 5XY1 isn't chip-8x's
+        let report = detect_variant(&[0x50, 0x11, 0x52, 0x31, 0x54, 0x51]);
+        assert_eq!(report.recommended, Variant::Chip8);
+        assert_eq!(report.quirks, Quirks::cosmac_vip());
+        assert_eq!(report.unsupported, None);
+    }
+
+    #[test]
+    fn chip8e_branches_are_followed() {
+        // branch forward to 206, branch back from there to 204 and halt
+        let rom = [0xBF, 0x06, 0x60, 0x01, 0x00, 0xFD, 0xBB, 0x02];
+        assert_eq!(code(&rom, Variant::Chip8E), [0x200, 0x204, 0x206]);
+    }
+
+    #[test]
+    fn skips_step_over_long_instructions() {
+        // skip if V0 is 0 over F000 0300, then halt
+        let rom = [0x30, 0x00, 0xF0, 0x00, 0x03, 0x00, 0x00, 0xFD];
+        assert_eq!(code(&rom, Variant::XoChip), [0x200, 0x202, 0x206]);
+        // without xo-chip F000 is a regular 2 byte instruction
+        assert_eq!(
+            code(&[0x30, 0x00, 0xF0, 0x00, 0x00, 0xFD], Variant::Chip8),
+            [0x200, 0x202, 0x204]
+        );
+    }
+}
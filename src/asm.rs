@@ -0,0 +1,475 @@
+//! a small assembler for writing test roms, using the mnemonics from the
+//! opcode reference table.
+//!
+//! one instruction or directive per line, `;` starts a comment:
+//!
+//! ```text
+//! start:  LD V0, 0x05     ; labels end with a colon
+//!         LD I, digits
+//!         DRW V0, V1, 5
+//!         JP start
+//! digits: db 0xF0, 0x90, 0b10010000, 144, 0xF0
+//! ```
+//!
+//! numbers are decimal, `0x` hex or `0b` binary, and anywhere a number goes a
+//! label can be used instead. mnemonics and registers are case insensitive
+
+use crate::emulator::core::START_ADDR;
+use crate::emulator::opcodes::{OPCODES, OpcodeInfo};
+use crate::emulator::variant::Variant;
+use std::collections::HashMap;
+use std::fmt;
+
+/// what went wrong and where, columns and lengths are in characters
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmError {
+    /// 1 based
+    pub line: usize,
+    /// 1 based
+    pub column: usize,
+    /// how many characters the error covers, at least 1
+    pub len: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// a piece of a line along with where it starts
+#[derive(Clone, Copy, Debug)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+    column: usize,
+}
+
+impl Token<'_> {
+    fn error(&self, message: impl Into<String>) -> AsmError {
+        AsmError {
+            line: self.line,
+            column: self.column,
+            len: self.text.chars().count().max(1),
+            message: message.into(),
+        }
+    }
+}
+
+enum Statement<'a> {
+    Data(Vec<Token<'a>>),
+    Instruction {
+        mnemonic: Token<'a>,
+        operands: Vec<Token<'a>>,
+    },
+}
+
+/// assemble `source` into a rom for `variant`, to be loaded at `START_ADDR`.
+/// a bad line doesn't stop the assembler, every error found is returned in source order
+pub fn assemble(source: &str, variant: Variant) -> Result<Vec<u8>, Vec<AsmError>> {
+    let (statements, mut errors) = parse(source);
+
+    // forward references aren't known on the first pass, they are assumed to fit
+    // the short forms. the second pass checks every instruction kept its size.
+    // statements that fail on the first pass are left out of the second
+    let mut labels = HashMap::new();
+    let mut sizes = Vec::with_capacity(statements.len());
+    let mut addr = START_ADDR;
+    for (label, statement) in &statements {
+        if let Some(label) = label {
+            let name = label.text.to_ascii_lowercase();
+            if labels.insert(name, addr).is_some() {
+                errors.push(label.error(format!("label {} is defined twice", label.text)));
+            }
+        }
+        let size = match statement {
+            Some(statement) => match emit(statement, variant, &labels, true) {
+                Ok(bytes) => Some(bytes.len()),
+                Err(err) => {
+                    errors.push(err);
+                    None
+                }
+            },
+            None => Some(0),
+        };
+        sizes.push(size);
+        addr += size.unwrap_or(0);
+    }
+
+    let mut rom = Vec::with_capacity(addr - START_ADDR);
+    for ((_, statement), size) in statements.iter().zip(sizes) {
+        let (Some(statement), Some(size)) = (statement, size) else {
+            continue;
+        };
+        let bytes = match emit(statement, variant, &labels, false) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                errors.push(err);
+                continue;
+            }
+        };
+        if bytes.len() != size {
+            let Statement::Instruction { mnemonic, .. } = statement else {
+                unreachable!("data doesn't depend on labels for its size");
+            };
+            errors.push(mnemonic.error(
+                "a label used here is defined later and needs the long form, define it earlier",
+            ));
+        }
+        rom.extend(bytes);
+    }
+
+    if errors.is_empty() {
+        Ok(rom)
+    } else {
+        errors.sort_by_key(|err| (err.line, err.column));
+        Err(errors)
+    }
+}
+
+type Line<'a> = (Option<Token<'a>>, Option<Statement<'a>>);
+
+/// the label and statement of every line that has one. a line with an error
+/// keeps its label if that is valid, so later uses of it don't fail too
+fn parse(source: &str) -> (Vec<Line<'_>>, Vec<AsmError>) {
+    let mut lines = Vec::new();
+    let mut errors = Vec::new();
+    for (idx, text) in source.lines().enumerate() {
+        let code = text.split(';').next().unwrap_or("");
+        let mut tokens = split(code, idx + 1);
+        if tokens.is_empty() {
+            continue;
+        }
+
+        let mut label = None;
+        if let Some(name) = tokens[0].text.strip_suffix(':') {
+            if is_label(name) {
+                label = Some(Token {
+                    text: name,
+                    ..tokens[0]
+                });
+            } else {
+                errors.push(tokens[0].error(format!("{name} is not a valid label name")));
+            }
+            tokens.remove(0);
+        }
+
+        let statement = match tokens.split_first() {
+            None => None,
+            Some((word, operands)) if word.text.eq_ignore_ascii_case("db") => {
+                if operands.is_empty() {
+                    errors.push(word.error("db needs at least one byte"));
+                    None
+                } else {
+                    Some(Statement::Data(operands.to_vec()))
+                }
+            }
+            Some((word, operands)) => Some(Statement::Instruction {
+                mnemonic: *word,
+                operands: operands.to_vec(),
+            }),
+        };
+        lines.push((label, statement));
+    }
+    (lines, errors)
+}
+
+/// the first word of `code` (and a label before it), then the comma separated
+/// operands, all trimmed
+fn split(code: &str, line: usize) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = code;
+    let mut offset = 0;
+
+    // the label and the mnemonic are separated by whitespace
+    for _ in 0..2 {
+        let trimmed = rest.trim_start();
+        offset += rest.len() - trimmed.len();
+        let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+        if end == 0 {
+            return tokens;
+        }
+        let word = &trimmed[..end];
+        tokens.push(token(code, word, offset, line));
+        offset += end;
+        rest = &trimmed[end..];
+        if !word.ends_with(':') {
+            break;
+        }
+    }
+
+    for operand in rest.split(',') {
+        let trimmed = operand.trim();
+        let start = offset + (operand.len() - operand.trim_start().len());
+        tokens.push(token(code, trimmed, start, line));
+        offset += operand.len() + 1;
+    }
+    // a mnemonic without operands still splits into one empty piece
+    if tokens.last().is_some_and(|last| last.text.is_empty()) && rest.trim().is_empty() {
+        tokens.pop();
+    }
+    tokens
+}
+
+fn token<'a>(code: &str, text: &'a str, offset: usize, line: usize) -> Token<'a> {
+    Token {
+        text,
+        line,
+        column: code[..offset].chars().count() + 1,
+    }
+}
+
+fn is_label(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && register(name).is_none()
+}
+
+fn register(text: &str) -> Option<u8> {
+    let digit = text.strip_prefix(['V', 'v'])?;
+    if digit.len() != 1 {
+        return None;
+    }
+    u8::from_str_radix(digit, 16).ok()
+}
+
+/// a literal or a label. on the first pass unknown labels are 0
+fn number(
+    token: &Token,
+    labels: &HashMap<String, usize>,
+    first_pass: bool,
+) -> Result<usize, AsmError> {
+    let text = token.text;
+    let parsed = if let Some(hex) = text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+        usize::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = text.strip_prefix("0b").or(text.strip_prefix("0B")) {
+        usize::from_str_radix(bin, 2).ok()
+    } else if text.starts_with(|c: char| c.is_ascii_digit()) {
+        text.parse().ok()
+    } else if is_label(text) {
+        match labels.get(&text.to_ascii_lowercase()) {
+            Some(addr) => Some(*addr),
+            None if first_pass => Some(0),
+            None => return Err(token.error(format!("unknown label {text}"))),
+        }
+    } else {
+        None
+    };
+    parsed.ok_or_else(|| token.error(format!("{text} is not a number or label")))
+}
+
+fn emit(
+    statement: &Statement,
+    variant: Variant,
+    labels: &HashMap<String, usize>,
+    first_pass: bool,
+) -> Result<Vec<u8>, AsmError> {
+    match statement {
+        Statement::Data(bytes) => bytes
+            .iter()
+            .map(|token| {
+                let val = number(token, labels, first_pass)?;
+                u8::try_from(val).map_err(|_| token.error(format!("{val} doesn't fit in a byte")))
+            })
+            .collect(),
+        Statement::Instruction { mnemonic, operands } => {
+            let candidates: Vec<&OpcodeInfo> = OPCODES
+                .iter()
+                .filter(|info| info.supported_by(variant))
+                .filter(|info| {
+                    let (name, pattern) = split_mnemonic(info.mnemonic);
+                    name.eq_ignore_ascii_case(mnemonic.text) && pattern.len() == operands.len()
+                })
+                .collect();
+            if candidates.is_empty() {
+                return Err(mnemonic.error(format!(
+                    "no {} instruction {} takes {} operands",
+                    variant.name(),
+                    mnemonic.text,
+                    operands.len()
+                )));
+            }
+
+            // the table lists more specific forms first, the first that fits wins.
+            // if none does, the candidate that matched the most operands explains it best
+            let mut best_error: Option<AsmError> = None;
+            for info in candidates {
+                match encode(info, operands, labels, first_pass) {
+                    Ok(bytes) => return Ok(bytes),
+                    Err(err) => {
+                        if best_error
+                            .as_ref()
+                            .is_none_or(|best| err.column > best.column)
+                        {
+                            best_error = Some(err);
+                        }
+                    }
+                }
+            }
+            Err(best_error.expect("there is at least one candidate"))
+        }
+    }
+}
+
+/// `"DRW VX, VY, N"` -> `("DRW", ["VX", "VY", "N"])`
+fn split_mnemonic(mnemonic: &str) -> (&str, Vec<&str>) {
+    match mnemonic.split_once(' ') {
+        Some((name, operands)) => (name, operands.split(", ").collect()),
+        None => (mnemonic, Vec::new()),
+    }
+}
+
+/// fill the operands into `info`'s opcode pattern
+fn encode(
+    info: &OpcodeInfo,
+    operands: &[Token],
+    labels: &HashMap<String, usize>,
+    first_pass: bool,
+) -> Result<Vec<u8>, AsmError> {
+    let (_, pattern) = split_mnemonic(info.mnemonic);
+    let mut x = 0;
+    let mut y = 0;
+    let mut n = 0;
+    let mut long = None;
+
+    for (kind, operand) in pattern.iter().zip(operands) {
+        match *kind {
+            "VX" | "VY" => {
+                let reg = register(operand.text).ok_or_else(|| {
+                    operand.error(format!("expected a register, not {}", operand.text))
+                })?;
+                if *kind == "VX" {
+                    x = reg;
+                } else {
+                    y = reg;
+                }
+            }
+            "N" | "NN" | "NNN" | "NNNN" | "0" => {
+                let val = number(operand, labels, first_pass)?;
+                let bits = if *kind == "0" { 0 } else { kind.len() * 4 };
+                if val >= 1 << bits {
+                    return Err(operand.error(format!("{val} doesn't fit in {bits} bits")));
+                }
+                if *kind == "NNNN" {
+                    long = Some(val as u16);
+                } else {
+                    n = val as u16;
+                }
+            }
+            literal => {
+                if !operand.text.eq_ignore_ascii_case(literal) {
+                    return Err(operand.error(format!("expected {literal}, not {}", operand.text)));
+                }
+            }
+        }
+    }
+
+    // hex digits in the pattern are literal, operands fill in the rest
+    let mut op = 0u16;
+    let mut n_shift = info.pattern.chars().filter(|c| *c == 'N').count() * 4;
+    for c in info.pattern.chars() {
+        let nibble = match c {
+            'X' => u16::from(x),
+            'Y' => u16::from(y),
+            'N' => {
+                n_shift -= 4;
+                (n >> n_shift) & 0xF
+            }
+            digit => digit
+                .to_digit(16)
+                .expect("patterns are hex digits and operands") as u16,
+        };
+        op = op << 4 | nibble;
+    }
+
+    let mut bytes = op.to_be_bytes().to_vec();
+    if let Some(long) = long {
+        bytes.extend_from_slice(&long.to_be_bytes());
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chip8(source: &str) -> Result<Vec<u8>, Vec<AsmError>> {
+        assemble(source, Variant::Chip8)
+    }
+
+    /// line, column, length and message of every error
+    fn errors(source: &str, variant: Variant) -> Vec<(usize, usize, usize, String)> {
+        assemble(source, variant)
+            .unwrap_err()
+            .into_iter()
+            .map(|err| (err.line, err.column, err.len, err.message))
+            .collect()
+    }
+
+    #[test]
+    fn labels() {
+        let rom = chip8("start: CLS\nloop:\n  JP loop\n  JP START");
+        assert_eq!(rom, Ok(vec![0x00, 0xE0, 0x12, 0x02, 0x12, 0x00]));
+    }
+
+    #[test]
+    fn forward_references() {
+        let rom = chip8("  LD I, data\n  CALL sub\nsub: RET\ndata: db 1");
+        assert_eq!(rom, Ok(vec![0xA2, 0x06, 0x22, 0x04, 0x00, 0xEE, 0x01]));
+    }
+
+    #[test]
+    fn data() {
+        let rom = chip8("db 0xF0, 0x90 ; two bytes\nDB 7");
+        assert_eq!(rom, Ok(vec![0xF0, 0x90, 0x07]));
+    }
+
+    #[test]
+    fn literal_forms() {
+        let rom = chip8("db 0x2A, 0X2a, 0b101010, 0B101010, 42\nld v3, 0xff");
+        assert_eq!(rom, Ok(vec![42, 42, 42, 42, 42, 0x63, 0xFF]));
+    }
+
+    #[test]
+    fn forward_reference_needing_the_long_form() {
+        // the label ends up past 0xFFF, which only F000 NNNN can load
+        let padding = vec!["0"; 0x1000].join(", ");
+        let source = format!("  LD I, far\n  db {padding}\nfar: db 1");
+        assert_eq!(
+            errors(&source, Variant::XoChip),
+            [(
+                1,
+                3,
+                2,
+                "a label used here is defined later and needs the long form, define it earlier"
+                    .to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn every_error_is_reported_with_its_span() {
+        let source = "  LD V0, 256\n  JP nowhere\n1bad: CLS\n  db 300, vx\n  FOO V1";
+        assert_eq!(
+            errors(source, Variant::Chip8),
+            [
+                (1, 10, 3, "256 doesn't fit in 8 bits".to_string()),
+                (2, 6, 7, "unknown label nowhere".to_string()),
+                (3, 1, 5, "1bad is not a valid label name".to_string()),
+                (4, 6, 3, "300 doesn't fit in a byte".to_string()),
+                (
+                    5,
+                    3,
+                    3,
+                    "no CHIP-8 instruction FOO takes 1 operands".to_string()
+                ),
+            ]
+        );
+    }
+}
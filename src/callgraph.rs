@@ -0,0 +1,106 @@
+//! runtime call graph built from `2NNN` / `00EE` hooks
+
+use crate::emulator::core::{Chip8, START_ADDR};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::rc::Rc;
+
+/// which subroutine called which, and how often.
+///
+/// nodes are subroutine entry addresses, the rom's entry point counts as one too
+#[derive(Clone, Debug)]
+pub struct CallGraph {
+    /// (caller entry, callee entry) -> number of calls
+    edges: BTreeMap<(usize, usize), usize>,
+    /// entry addresses of the subroutines currently on the call stack
+    frames: Vec<usize>,
+    labels: HashMap<usize, String>,
+}
+
+impl Default for CallGraph {
+    fn default() -> Self {
+        Self {
+            edges: BTreeMap::new(),
+            frames: vec![START_ADDR],
+            labels: HashMap::new(),
+        }
+    }
+}
+
+impl CallGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// register the hooks needed to build a call graph while `chip8` runs
+    pub fn attach(chip8: &mut Chip8) -> Rc<RefCell<CallGraph>> {
+        let graph = Rc::new(RefCell::new(CallGraph::new()));
+
+        let on_call = Rc::clone(&graph);
+        chip8.add_hook(&["2NNN"], move |ins| {
+            on_call.borrow_mut().record_call(usize::from(ins.nnn));
+        });
+        let on_return = Rc::clone(&graph);
+        chip8.add_hook(&["00EE"], move |_| on_return.borrow_mut().record_return());
+
+        graph
+    }
+
+    /// current subroutine calls `callee`
+    pub fn record_call(&mut self, callee: usize) {
+        let caller = self.current();
+        *self.edges.entry((caller, callee)).or_insert(0) += 1;
+        self.frames.push(callee);
+    }
+
+    pub fn record_return(&mut self) {
+        // never pop the entry point, a stray 00EE shouldn't lose track of it
+        if self.frames.len() > 1 {
+            self.frames.pop();
+        }
+    }
+
+    fn current(&self) -> usize {
+        *self.frames.last().expect("entry point is never popped")
+    }
+
+    /// name a subroutine in the exported graph
+    pub fn set_label(&mut self, addr: usize, label: impl Into<String>) {
+        self.labels.insert(addr, label.into());
+    }
+
+    /// (caller, callee, count), sorted by address
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        self.edges
+            .iter()
+            .map(|(&(caller, callee), &count)| (caller, callee, count))
+    }
+
+    fn node_name(&self, addr: usize) -> String {
+        match self.labels.get(&addr) {
+            Some(label) => format!("{label}\\n0x{addr:03X}"),
+            None => format!("0x{addr:03X}"),
+        }
+    }
+
+    /// export as a graphviz digraph, edges labelled with their call count
+    pub fn to_dot(&self) -> String {
+        let mut nodes: Vec<usize> = self.edges.keys().flat_map(|&(a, b)| [a, b]).collect();
+        nodes.sort_unstable();
+        nodes.dedup();
+
+        let mut dot = String::from("digraph calls {\n    node [shape=box];\n");
+        for node in nodes {
+            let _ = writeln!(dot, "    n{node:03X} [label=\"{}\"];", self.node_name(node));
+        }
+        for (caller, callee, count) in self.edges() {
+            let _ = writeln!(
+                dot,
+                "    n{caller:03X} -> n{callee:03X} [label=\"{count}\"];"
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
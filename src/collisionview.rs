@@ -0,0 +1,133 @@
+//! debug view of the screen that flashes the pixels sprite collisions turned
+//! off, for checking a rom's hit detection. combine with
+//! `Chip8::set_pause_on_collision` to stop on the first one
+
+use crate::emulator::core::Chip8;
+use crate::emulator::state::Screen;
+
+/// frames a collision keeps flashing for by default, a quarter second at 60Hz
+pub const DEFAULT_FLASH_FRAMES: u32 = 15;
+
+/// what to show for a pixel of the collision view
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CollisionPixel {
+    #[default]
+    Off,
+    /// a color index as in `Screen::get_color`, never 0
+    Lit(u8),
+    /// a pixel a collision turned off, to be drawn in a color of its own
+    Collision,
+}
+
+/// the collisions of the last few frames, see `Chip8::collisions`
+#[derive(Clone, Debug)]
+pub struct CollisionFlash {
+    /// x, y and how many more frames to show it for
+    pixels: Vec<(usize, usize, u32)>,
+    frames: u32,
+    hires: bool,
+}
+
+impl Default for CollisionFlash {
+    fn default() -> Self {
+        Self::new(DEFAULT_FLASH_FRAMES)
+    }
+}
+
+impl CollisionFlash {
+    /// keep every collision flashing for `frames` frames
+    pub fn new(frames: u32) -> Self {
+        Self {
+            pixels: Vec::new(),
+            frames,
+            hires: false,
+        }
+    }
+
+    /// call once per frame, after the frame ran
+    pub fn record(&mut self, chip8: &Chip8) {
+        let hires = chip8.get_screen().is_hires();
+        if hires != self.hires {
+            // the coordinates are for the old resolution
+            self.pixels.clear();
+            self.hires = hires;
+        }
+        for pixel in self.pixels.iter_mut() {
+            pixel.2 -= 1;
+        }
+        self.pixels.retain(|(_, _, remaining)| *remaining > 0);
+        if self.frames > 0 {
+            let frames = self.frames;
+            self.pixels
+                .extend(chip8.collisions().iter().map(|&(x, y)| (x, y, frames)));
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.pixels.clear();
+    }
+
+    /// whether a collision is flashing at (x, y), on alternate frames so it blinks
+    pub fn is_flashing(&self, x: usize, y: usize) -> bool {
+        self.pixels
+            .iter()
+            .any(|&(px, py, remaining)| (px, py) == (x, y) && remaining % 2 == self.frames % 2)
+    }
+
+    /// call `plot(x, y, pixel)` for every pixel of `screen`
+    pub fn draw_with(&self, screen: &Screen, mut plot: impl FnMut(usize, usize, CollisionPixel)) {
+        for y in 0..screen.height() {
+            for x in 0..screen.width() {
+                let pixel = match screen.get_color(x, y) {
+                    _ if self.is_flashing(x, y) => CollisionPixel::Collision,
+                    0 => CollisionPixel::Off,
+                    color => CollisionPixel::Lit(color),
+                };
+                plot(x, y, pixel);
+            }
+        }
+    }
+
+    /// `draw_with` into a row-major buffer the size of the screen
+    pub fn draw(&self, screen: &Screen, buf: &mut [CollisionPixel]) {
+        let width = screen.width();
+        self.draw_with(screen, |x, y, pixel| {
+            if let Some(cell) = buf.get_mut(y * width + x) {
+                *cell = pixel;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collisions_blink_then_expire() {
+        let mut chip8 = Chip8::new();
+        // I = the 0 glyph, draw it twice at 0, 0, then loop
+        chip8
+            .load_rom(&[0xF0, 0x29, 0xD0, 0x05, 0xD0, 0x05, 0x12, 0x06])
+            .unwrap();
+        let mut flash = CollisionFlash::new(3);
+        let mut buf = vec![CollisionPixel::Off; 64 * 32];
+
+        let mut shown = Vec::new();
+        for _ in 0..4 {
+            chip8.step_frame(4).unwrap();
+            flash.record(&chip8);
+            flash.draw(chip8.get_screen(), &mut buf);
+            shown.push(buf[0]);
+        }
+        assert_eq!(
+            shown,
+            [
+                CollisionPixel::Collision,
+                CollisionPixel::Off,
+                CollisionPixel::Collision,
+                CollisionPixel::Off,
+            ]
+        );
+    }
+}
@@ -1,3 +1,13 @@
 pub mod core;
+pub mod error;
 pub mod fontset;
+pub mod hooks;
+pub mod host;
+pub mod instruction;
+pub mod memory;
+pub mod opcodes;
+pub mod quirks;
+pub mod schedule;
+pub mod snapshot;
 pub mod state;
+pub mod variant;
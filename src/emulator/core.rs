@@ -1,18 +1,53 @@
-use super::fontset::{FONTSET, FONTSET_SIZE};
-use super::state::{ProgramState, Screen, TimerState};
+use super::error::Chip8Error;
+use super::fontset::{
+    BIG_FONTSET, BIG_FONTSET_SIZE, BIG_GLYPH_SIZE, FONTSET, FONTSET_ADDR, FONTSET_SIZE, GLYPH_SIZE,
+};
+use super::hooks::{DebugOutput, HookId, Hooks, LifecycleListeners, Trace};
+use super::host::HostDevice;
+use super::instruction::Instruction;
+use super::memory::{BankWindow, Memory, MemorySize, RamInit, WriteProtection};
+use super::quirks::Quirks;
+use super::schedule::{InputSampling, SchedulePolicy, TimerTick};
+use super::snapshot::Snapshot;
+use super::state::{
+    Access, DrawCall, FrameResult, Lifecycle, NUM_PLANES, ProgramState, ProtectedWrite, Register,
+    RegisterDelta, Screen, ScreenRegion, ScreenWatchHit, TimerState, TraceEvent, Watchpoint,
+};
+use super::variant::Variant;
+#[cfg(feature = "timing")]
+use crate::pacer::FramePacer;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use std::collections::BTreeSet;
+#[cfg(feature = "timing")]
+use std::time::Duration;
 
 pub const SCREEN_WIDTH: usize = 64;
 pub const SCREEN_HEIGHT: usize = 32;
+pub const HIRES_SCREEN_WIDTH: usize = 128;
+pub const HIRES_SCREEN_HEIGHT: usize = 64;
 
-const RAM_SIZE: usize = 4 * 1024;
-const NUM_V_REGS: usize = 16;
+pub const NUM_V_REGS: usize = 16;
 const STACK_SIZE: usize = 16;
-const NUM_KEYS: usize = 16;
-const START_ADDR: usize = 0x200;
+/// schip calculators had 8 rpl user flags, xo-chip has 16
+pub const NUM_RPL_FLAGS: usize = 16;
+pub const NUM_KEYS: usize = 16;
+pub const AUDIO_PATTERN_SIZE: usize = 16;
+/// 4000Hz playback of the audio pattern
+pub const DEFAULT_PITCH: u8 = 64;
+pub const START_ADDR: usize = 0x200;
+/// `0FF2` stops here if it never finds the terminating zero
+const MAX_DEBUG_STRING: usize = 256;
 
+/// a chip8 machine.
+///
+/// cloning is the supported way to fork execution (rollback, stepping back in a
+/// debugger, exploring input branches), a clone shares nothing with the original.
+/// execution hooks are not carried over, the fork starts without any
+#[derive(Clone)]
 pub struct Chip8 {
     program_counter: usize,
-    memory: [u8; RAM_SIZE],
+    memory: Memory,
 
     v_regs: [u8; NUM_V_REGS],
     i_reg: u16,
@@ -21,81 +56,864 @@ pub struct Chip8 {
 
     screen: Screen,
     keys: [bool; NUM_KEYS],
+    /// keys as sampled at the start of the frame, for the latched_keypad quirk
+    latched_keys: [bool; NUM_KEYS],
+    /// key pressed while FX0A waits for it to be released, for the latched_keypad quirk
+    key_wait_pressed: Option<u8>,
+    /// chip-8e FX4F is blocked on the delay timer it set
+    delay_wait: bool,
 
     delay_timer: u8,
     sound_timer: u8,
+    /// schip `FX75`/`FX85` storage, kept by real hardware across programs
+    rpl_flags: [u8; NUM_RPL_FLAGS],
+    /// xo-chip 1 bit samples played while the sound timer runs, set by `F002`
+    audio_pattern: [u8; AUDIO_PATTERN_SIZE],
+    /// xo-chip `FX3A`, playback rate is 4000 * 2^((pitch - 64) / 48) Hz
+    pitch: u8,
+
+    variant: Variant,
+    quirks: Quirks,
+    schedule: SchedulePolicy,
+    /// see `set_font_addr`
+    font_addr: usize,
+    /// source of `CXNN`'s random bytes, a clone continues the same sequence.
+    /// chacha rather than `StdRng` so a snapshot can save its position
+    rng: ChaCha12Rng,
 
     // not part of the chip8 spec, just for use in this emulator
     _finished: bool,
+    /// set when the rom itself stopped the machine with `00FD`
+    exit_requested: bool,
+    paused: bool,
+    hooks: Hooks,
+    /// set by `tick_timers` (and initially), the first instruction of the next frame
+    /// clears per-frame debug info and latches the keys
+    frame_done: bool,
+    collisions: Vec<(usize, usize)>,
+    pause_on_collision: bool,
+    draw_calls: Vec<DrawCall>,
+    record_draw_calls: bool,
+    screen_watches: Vec<ScreenRegion>,
+    screen_watch_hit: Option<ScreenWatchHit>,
+    debug_output: DebugOutput,
+    trace: Trace,
+    breakpoints: BTreeSet<usize>,
+    /// paused right before a breakpoint, the next cycle executes it instead of stopping again
+    stopped_at_breakpoint: bool,
+    rom_loaded: bool,
+    write_protection: WriteProtection,
+    watchpoints: Vec<Watchpoint>,
+    /// first watched access of the executing instruction
+    watchpoint_hit: Option<ProgramState>,
+    protected_writes: Vec<ProtectedWrite>,
+    /// error of the last instruction, cleared when one executes
+    fault: Option<Chip8Error>,
+    /// as last reported to the listeners
+    last_lifecycle: Lifecycle,
+    lifecycle_listeners: LifecycleListeners,
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Chip8 {
     pub fn new() -> Self {
+        Self::new_with_memory_size(MemorySize::Standard)
+    }
+
+    /// a machine running the given dialect's instruction set, with the quirks its
+    /// roms usually expect (`Quirks::for_variant`)
+    pub fn new_with_variant(variant: Variant) -> Self {
+        Self::new_with_variant_and_quirks(variant, Quirks::for_variant(variant))
+    }
+
+    /// a plain chip8 machine with the given behaviour, e.g. `Quirks::cosmac_vip()`
+    pub fn new_with_quirks(quirks: Quirks) -> Self {
+        Self::new_with_variant_and_quirks(Variant::Chip8, quirks)
+    }
+
+    /// a machine running `variant`'s instruction set with the given behaviour
+    pub fn new_with_variant_and_quirks(variant: Variant, quirks: Quirks) -> Self {
+        let mut new = Self::new_with_memory_size(variant.memory_size());
+        new.variant = variant;
+        new.quirks = quirks;
+        new
+    }
+
+    pub fn new_with_memory_size(size: MemorySize) -> Self {
         let mut new = Self {
             program_counter: START_ADDR,
-            memory: [0; RAM_SIZE],
+            memory: Memory::new(size),
             v_regs: [0; NUM_V_REGS],
             i_reg: 0,
             stack: [0; STACK_SIZE],
             stack_pointer: 0,
             screen: Screen::new(),
             keys: [false; NUM_KEYS],
+            latched_keys: [false; NUM_KEYS],
+            key_wait_pressed: None,
+            delay_wait: false,
             delay_timer: 0,
             sound_timer: 0,
+            rpl_flags: [0; NUM_RPL_FLAGS],
+            audio_pattern: [0; AUDIO_PATTERN_SIZE],
+            pitch: DEFAULT_PITCH,
+            variant: Variant::Chip8,
+            quirks: Quirks::default(),
+            schedule: SchedulePolicy::default(),
+            rng: ChaCha12Rng::from_os_rng(),
+            font_addr: FONTSET_ADDR,
 
             _finished: false,
+            exit_requested: false,
+            paused: false,
+            hooks: Hooks::default(),
+            frame_done: true,
+            collisions: Vec::new(),
+            pause_on_collision: false,
+            draw_calls: Vec::new(),
+            record_draw_calls: false,
+            screen_watches: Vec::new(),
+            screen_watch_hit: None,
+            debug_output: DebugOutput::default(),
+            trace: Trace::default(),
+            breakpoints: BTreeSet::new(),
+            stopped_at_breakpoint: false,
+            rom_loaded: false,
+            write_protection: WriteProtection::Off,
+            watchpoints: Vec::new(),
+            watchpoint_hit: None,
+            protected_writes: Vec::new(),
+            fault: None,
+            last_lifecycle: Lifecycle::Idle,
+            lifecycle_listeners: LifecycleListeners::default(),
         };
         new.copy_fontset();
         new
     }
 
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    pub fn schedule(&self) -> SchedulePolicy {
+        self.schedule
+    }
+
+    /// choose when input is sampled and timers tick within a frame, takes effect
+    /// from the next frame on
+    pub fn set_schedule(&mut self, schedule: SchedulePolicy) {
+        self.schedule = schedule;
+    }
+
+    /// make `CXNN` draw from a sequence seeded with `seed`, for reproducible runs and
+    /// replays. machines start seeded from system entropy
+    pub fn set_rng_seed(&mut self, seed: u64) {
+        self.rng = ChaCha12Rng::seed_from_u64(seed);
+    }
+
+    /// the display, `Screen::get_color` and `Screen::iter_screen` give per-plane
+    /// color indices for xo-chip, `Screen::get_pixel` whether anything is lit
+    pub fn get_screen(&self) -> &Screen {
+        &self.screen
+    }
+
+    /// the part of the screen changed since the last call, see `Screen::take_dirty`
+    pub fn take_screen_dirty(&mut self) -> Option<ScreenRegion> {
+        self.screen.take_dirty()
+    }
+
+    /// V0..VF
+    pub fn registers(&self) -> &[u8; NUM_V_REGS] {
+        &self.v_regs
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    /// address of the next instruction to execute
+    pub fn program_counter(&self) -> usize {
+        self.program_counter
+    }
+
+    pub fn i_reg(&self) -> u16 {
+        self.i_reg
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    /// return addresses of the active calls, outermost first
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.stack_pointer]
+    }
+
+    /// copy a rom into memory at `START_ADDR`
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), Chip8Error> {
+        let max = self.memory.size() - START_ADDR;
+        if rom.len() > max {
+            return Err(Chip8Error::RomTooLarge {
+                size: rom.len(),
+                max,
+            });
+        }
+        self.memory.write_range(START_ADDR, rom);
+        self.rom_loaded = true;
+        self.update_lifecycle();
+        Ok(())
+    }
+
+    /// capture the machine so `load_state` can return to this point later
+    pub fn save_state(&self) -> Snapshot {
+        Snapshot {
+            program_counter: self.program_counter as u32,
+            i_reg: self.i_reg,
+            v_regs: self.v_regs,
+            stack: self.stack,
+            stack_pointer: self.stack_pointer as u8,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            keys: self.keys,
+            latched_keys: self.latched_keys,
+            key_wait_pressed: self.key_wait_pressed,
+            delay_wait: self.delay_wait,
+            finished: self._finished,
+            exit_requested: self.exit_requested,
+            variant: self.variant,
+            quirks: self.quirks,
+            schedule: self.schedule,
+            font_addr: self.font_addr as u16,
+            rpl_flags: self.rpl_flags,
+            audio_pattern: self.audio_pattern,
+            pitch: self.pitch,
+            hires: self.screen.is_hires(),
+            planes: self.screen.selected_planes(),
+            rng_seed: self.rng.get_seed(),
+            rng_word_pos: self.rng.get_word_pos(),
+            bank: self.memory.bank() as u16,
+            memory: self.memory.as_slice().to_vec(),
+            screen: self.screen.cells().to_vec(),
+        }
+    }
+
+    /// return to a snapshot taken from a machine with the same memory size and
+    /// bank window, including its variant and quirks
+    pub fn load_state(&mut self, snapshot: &Snapshot) -> Result<(), Chip8Error> {
+        if snapshot.memory.len() != self.memory.as_slice().len() {
+            return Err(Chip8Error::InvalidSnapshot(
+                "memory size differs from this machine's",
+            ));
+        }
+        if snapshot.screen.len() != self.screen.cells().len() {
+            return Err(Chip8Error::InvalidSnapshot("screen size is wrong"));
+        }
+        if usize::from(snapshot.stack_pointer) > STACK_SIZE {
+            return Err(Chip8Error::InvalidSnapshot("stack pointer past the stack"));
+        }
+        if usize::from(snapshot.font_addr) + FONTSET_SIZE + BIG_FONTSET_SIZE > START_ADDR {
+            return Err(Chip8Error::InvalidSnapshot("fonts overlap the program"));
+        }
+        let banks = self.memory.bank_window().map_or(1, |window| window.banks);
+        if usize::from(snapshot.bank) >= banks {
+            return Err(Chip8Error::InvalidSnapshot("selected bank doesn't exist"));
+        }
+
+        self.program_counter = snapshot.program_counter as usize;
+        self.i_reg = snapshot.i_reg;
+        self.v_regs = snapshot.v_regs;
+        self.stack = snapshot.stack;
+        self.stack_pointer = usize::from(snapshot.stack_pointer);
+        self.delay_timer = snapshot.delay_timer;
+        self.sound_timer = snapshot.sound_timer;
+        self.keys = snapshot.keys;
+        self.latched_keys = snapshot.latched_keys;
+        self.key_wait_pressed = snapshot.key_wait_pressed;
+        self.delay_wait = snapshot.delay_wait;
+        self._finished = snapshot.finished;
+        self.exit_requested = snapshot.exit_requested;
+        self.variant = snapshot.variant;
+        self.quirks = snapshot.quirks;
+        self.schedule = snapshot.schedule;
+        self.font_addr = usize::from(snapshot.font_addr);
+        self.rpl_flags = snapshot.rpl_flags;
+        self.audio_pattern = snapshot.audio_pattern;
+        self.pitch = snapshot.pitch;
+        self.memory.restore(&snapshot.memory);
+        self.memory.select_bank(usize::from(snapshot.bank));
+        self.rng = ChaCha12Rng::from_seed(snapshot.rng_seed);
+        self.rng.set_word_pos(snapshot.rng_word_pos);
+        self.screen
+            .restore(&snapshot.screen, snapshot.hires, snapshot.planes);
+
+        self.frame_done = true;
+        self.stopped_at_breakpoint = false;
+        self.rom_loaded = true;
+        self.fault = None;
+        self.update_lifecycle();
+        Ok(())
+    }
+
+    /// route reads and writes of `start..start + len` to a host device instead of ram,
+    /// e.g. a `host::Clock` or `host::CharOutput` for test roms to report through.
+    /// off by default, and not carried over when the machine is cloned
+    pub fn map_host_device(
+        &mut self,
+        start: usize,
+        len: usize,
+        device: impl HostDevice + 'static,
+    ) -> Result<(), Chip8Error> {
+        if start + len > self.memory.size() {
+            return Err(Chip8Error::MemoryOutOfBounds {
+                addr: start + len - 1,
+            });
+        }
+        self.memory.map_device(start, len, device);
+        Ok(())
+    }
+
+    pub fn unmap_host_devices(&mut self) {
+        self.memory.unmap_devices();
+    }
+
+    /// let `window.start..window.start + window.len` switch between `window.banks`
+    /// banks of ram, for configurations that need more than the address space holds.
+    /// bank 0 is the ram the window covers anyway and starts out selected, the other
+    /// banks start zeroed. `None` removes the window along with the extra banks
+    pub fn set_bank_window(&mut self, window: Option<BankWindow>) -> Result<(), Chip8Error> {
+        if let Some(window) = window {
+            if window.len == 0 || window.start + window.len > self.memory.size() {
+                return Err(Chip8Error::MemoryOutOfBounds {
+                    addr: window.start + window.len.max(1) - 1,
+                });
+            }
+            if window.banks == 0 {
+                return Err(Chip8Error::NoSuchBank { bank: 0 });
+            }
+        }
+        self.memory.set_bank_window(window);
+        Ok(())
+    }
+
+    /// show `bank` in the bank window from the next instruction on
+    pub fn select_bank(&mut self, bank: usize) -> Result<(), Chip8Error> {
+        let banks = self.memory.bank_window().map_or(1, |window| window.banks);
+        if bank >= banks {
+            return Err(Chip8Error::NoSuchBank { bank });
+        }
+        self.memory.select_bank(bank);
+        Ok(())
+    }
+
+    /// fill ram with `init` and put the font back, call before loading a rom
+    pub fn init_ram(&mut self, init: RamInit) {
+        self.memory.fill(init);
+        self.copy_fontset();
+    }
+
     pub fn copy_fontset(&mut self) {
-        self.memory[..FONTSET_SIZE].copy_from_slice(&FONTSET);
+        self.memory.write_range(self.font_addr, &FONTSET);
+        self.memory
+            .write_range(self.font_addr + FONTSET_SIZE, &BIG_FONTSET);
     }
 
-    /// call to progress the emulator
-    pub fn tick(&mut self) -> ProgramState {
-        if self._finished || self.program_counter > RAM_SIZE - 2 {
-            return ProgramState::Finished;
+    /// load the fonts at `addr` instead of `FONTSET_ADDR`, e.g. 0x50 like many
+    /// interpreters do, and point `FX29`/`FX30` there. the old font area is zeroed.
+    /// both fonts have to fit below `START_ADDR`. call before loading a rom
+    pub fn set_font_addr(&mut self, addr: usize) -> Result<(), Chip8Error> {
+        let end = addr + FONTSET_SIZE + BIG_FONTSET_SIZE;
+        if end > START_ADDR {
+            return Err(Chip8Error::MemoryOutOfBounds { addr: end - 1 });
         }
+        self.memory
+            .write_range(self.font_addr, &[0; FONTSET_SIZE + BIG_FONTSET_SIZE]);
+        self.font_addr = addr;
+        self.copy_fontset();
+        Ok(())
+    }
 
-        let higher = self.memory[self.program_counter] as u16;
-        let lower = self.memory[self.program_counter + 1] as u16;
-        let op = higher << 8 | lower;
-        self.exec_op(op);
+    /// where the 4x5 font starts, the 8x10 one follows it
+    pub fn font_addr(&self) -> usize {
+        self.font_addr
+    }
 
-        return match self.checked_pc_increment(2usize) {
-            Err(_) => ProgramState::Finished,
-            Ok(_) => ProgramState::Running,
-        };
+    /// the schip rpl user flags, for frontends that persist them between runs
+    pub fn rpl_flags(&self) -> &[u8; NUM_RPL_FLAGS] {
+        &self.rpl_flags
+    }
+
+    pub fn set_rpl_flags(&mut self, flags: [u8; NUM_RPL_FLAGS]) {
+        self.rpl_flags = flags;
+    }
+
+    /// the xo-chip audio pattern, 128 1 bit samples msb first
+    pub fn audio_pattern(&self) -> &[u8; AUDIO_PATTERN_SIZE] {
+        &self.audio_pattern
     }
 
-    /// call once per frame, returns whether to play sound or not
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    /// call to progress the emulator.
+    /// on an error the program counter is left on the faulting instruction
+    pub fn tick(&mut self) -> Result<ProgramState, Chip8Error> {
+        if self.paused {
+            return Ok(ProgramState::Paused);
+        }
+        self.cycle()
+    }
+
+    fn cycle(&mut self) -> Result<ProgramState, Chip8Error> {
+        let res = self.execute_next();
+        self.fault = res.err();
+        self.update_lifecycle();
+        res
+    }
+
+    fn execute_next(&mut self) -> Result<ProgramState, Chip8Error> {
+        if self.frame_done {
+            self.frame_done = false;
+            self.collisions.clear();
+            self.draw_calls.clear();
+            self.protected_writes.clear();
+            self.latched_keys = self.keys;
+        }
+
+        if self._finished || self.program_counter > self.memory.size() - 2 {
+            return Ok(self.halted_state());
+        }
+        if !self.stopped_at_breakpoint && self.breakpoints.contains(&self.program_counter) {
+            self.stopped_at_breakpoint = true;
+            self.paused = true;
+            return Ok(ProgramState::Paused);
+        }
+        self.stopped_at_breakpoint = false;
+
+        let op = self.memory.read_u16(self.program_counter);
+        if !self.hooks.is_empty() {
+            self.hooks
+                .run(&Instruction::decode(self.program_counter, op));
+        }
+        let watched = (!self.screen_watches.is_empty()).then(|| self.watched_pixels());
+        let regs_before = self.trace.is_enabled().then_some((self.v_regs, self.i_reg));
+        // the program counter already points at the next instruction while this one
+        // executes, so jumps and calls can set it directly
+        let addr = self.program_counter;
+        self.program_counter += 2;
+        if let Err(err) = self.exec_op(op) {
+            self.program_counter = addr;
+            self.watchpoint_hit = None;
+            return Err(err);
+        }
+        if let Some(before) = watched {
+            self.check_screen_watches(&before, addr, op);
+        }
+        if let Some((v_before, i_before)) = regs_before {
+            self.record_trace(Instruction::decode(addr, op), v_before, i_before);
+        }
+        if self._finished || self.program_counter > self.memory.size() - 2 {
+            self._finished = true;
+            return Ok(self.halted_state());
+        }
+        if let Some(hit) = self.watchpoint_hit.take() {
+            self.paused = true;
+            return Ok(hit);
+        }
+
+        Ok(ProgramState::Running)
+    }
+
+    fn record_trace(
+        &mut self,
+        instruction: Instruction,
+        v_before: [u8; NUM_V_REGS],
+        i_before: u16,
+    ) {
+        let mut deltas: Vec<RegisterDelta> = (0..NUM_V_REGS)
+            .filter(|idx| v_before[*idx] != self.v_regs[*idx])
+            .map(|idx| RegisterDelta {
+                register: Register::V(idx as u8),
+                before: u16::from(v_before[idx]),
+                after: u16::from(self.v_regs[idx]),
+            })
+            .collect();
+        if i_before != self.i_reg {
+            deltas.push(RegisterDelta {
+                register: Register::I,
+                before: i_before,
+                after: self.i_reg,
+            });
+        }
+        self.trace.record(TraceEvent {
+            instruction,
+            next_pc: self.program_counter,
+            deltas,
+        });
+    }
+
+    fn halted_state(&self) -> ProgramState {
+        ProgramState::Halted {
+            requested_by_rom: self.exit_requested,
+        }
+    }
+
+    /// call once per frame, returns whether to play sound or not.
+    /// timers are frozen while paused and the sound is reported as off
     pub fn tick_timers(&mut self) -> TimerState {
+        if self.paused {
+            return TimerState::None;
+        }
+        self.cycle_timers()
+    }
+
+    fn cycle_timers(&mut self) -> TimerState {
+        self.frame_done = true;
+
         if self.delay_timer > 0 {
             self.delay_timer -= 1;
         }
 
         if self.sound_timer > 0 {
-            if self.sound_timer == 1 {
-                return TimerState::PlaySound;
-            }
             self.sound_timer -= 1;
+            return TimerState::PlaySound;
         }
 
         TimerState::None
     }
 
+    /// stop `tick` and `tick_timers` from advancing the machine
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.update_lifecycle();
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.screen_watch_hit = None;
+        self.update_lifecycle();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// where the machine is in its lifecycle, see `Lifecycle` for the transitions
+    pub fn lifecycle(&self) -> Lifecycle {
+        if !self.rom_loaded {
+            Lifecycle::Idle
+        } else if let Some(err) = self.fault {
+            Lifecycle::Faulted(err)
+        } else if self._finished {
+            Lifecycle::Halted {
+                requested_by_rom: self.exit_requested,
+            }
+        } else if self.paused {
+            Lifecycle::Paused
+        } else if let Some(register) = self.waiting_for_key() {
+            Lifecycle::WaitingForInput { register }
+        } else {
+            Lifecycle::Running
+        }
+    }
+
+    /// call `listener` with the old and new lifecycle whenever it changes.
+    /// not carried over when the machine is cloned
+    pub fn on_lifecycle_change(
+        &mut self,
+        listener: impl FnMut(Lifecycle, Lifecycle) + 'static,
+    ) -> HookId {
+        self.lifecycle_listeners.add(listener)
+    }
+
+    pub fn remove_lifecycle_listener(&mut self, id: HookId) -> bool {
+        self.lifecycle_listeners.remove(id)
+    }
+
+    fn update_lifecycle(&mut self) {
+        let now = self.lifecycle();
+        if now != self.last_lifecycle {
+            let before = std::mem::replace(&mut self.last_lifecycle, now);
+            self.lifecycle_listeners.notify(before, now);
+        }
+    }
+
+    /// pause the machine right before the instruction at `addr` executes.
+    /// returns false if there already was a breakpoint there
+    pub fn add_breakpoint(&mut self, addr: usize) -> bool {
+        self.breakpoints.insert(addr)
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: usize) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// the breakpoint the machine is stopped at, until the instruction there executes
+    pub fn breakpoint_hit(&self) -> Option<usize> {
+        self.stopped_at_breakpoint.then_some(self.program_counter)
+    }
+
+    /// execute exactly one instruction, even while paused or stopped at a breakpoint.
+    /// the machine stays paused if it was
+    pub fn step(&mut self) -> Result<ProgramState, Chip8Error> {
+        if self.breakpoints.contains(&self.program_counter) {
+            self.stopped_at_breakpoint = true;
+        }
+        self.cycle()
+    }
+
+    /// resume and run up to `max_cycles` instructions, stopping early at a breakpoint
+    /// or a halt. timers aren't ticked, a frontend's frame loop does that.
+    /// returns `Running` if none of them was reached
+    pub fn continue_until_break(&mut self, max_cycles: u32) -> Result<ProgramState, Chip8Error> {
+        self.resume();
+        for _ in 0..max_cycles {
+            let state = self.tick()?;
+            if state != ProgramState::Running {
+                return Ok(state);
+            }
+        }
+        Ok(ProgramState::Running)
+    }
+
+    /// run `hook` before every instruction whose family (e.g. `"DXYN"`, `"2NNN"`)
+    /// is in `families`, see `analysis::opcode_family` for the names
+    pub fn add_hook(
+        &mut self,
+        families: &[&'static str],
+        hook: impl FnMut(&Instruction) + 'static,
+    ) -> HookId {
+        self.hooks.add(Some(families), hook)
+    }
+
+    /// run `hook` before every instruction
+    pub fn add_hook_all(&mut self, hook: impl FnMut(&Instruction) + 'static) -> HookId {
+        self.hooks.add(None, hook)
+    }
+
+    pub fn remove_hook(&mut self, id: HookId) -> bool {
+        self.hooks.remove(id)
+    }
+
+    /// pixels turned off by sprite collisions during the current frame,
+    /// kept until the first instruction of the next frame
+    pub fn collisions(&self) -> &[(usize, usize)] {
+        &self.collisions
+    }
+
+    /// guard the interpreter area and the fonts below `START_ADDR` against stray writes
+    pub fn set_write_protection(&mut self, protection: WriteProtection) {
+        self.write_protection = protection;
+    }
+
+    /// writes below `START_ADDR` during the current frame with `WriteProtection::Warn`,
+    /// kept until the first instruction of the next frame
+    pub fn protected_writes(&self) -> &[ProtectedWrite] {
+        &self.protected_writes
+    }
+
+    /// pause the machine right after a `DXYN` that collided
+    pub fn set_pause_on_collision(&mut self, enabled: bool) {
+        self.pause_on_collision = enabled;
+    }
+
+    /// sprites drawn during the current frame, if recording is enabled,
+    /// kept until the first instruction of the next frame
+    pub fn draw_calls(&self) -> &[DrawCall] {
+        &self.draw_calls
+    }
+
+    pub fn set_record_draw_calls(&mut self, enabled: bool) {
+        self.record_draw_calls = enabled;
+        if !enabled {
+            self.draw_calls.clear();
+        }
+    }
+
+    /// pause the machine after any instruction that reads or writes watched memory,
+    /// `tick` returns `ProgramState::WatchpointHit` for it. instruction fetches don't count.
+    /// returns the index of the watchpoint
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) -> usize {
+        self.watchpoints.push(watchpoint);
+        self.watchpoints.len() - 1
+    }
+
+    pub fn clear_watchpoints(&mut self) {
+        self.watchpoints.clear();
+    }
+
+    /// pause the machine after any instruction that changes a pixel inside `region`,
+    /// returns the index reported in `ScreenWatchHit::watch`
+    pub fn add_screen_watch(&mut self, region: ScreenRegion) -> usize {
+        self.screen_watches.push(region);
+        self.screen_watches.len() - 1
+    }
+
+    pub fn clear_screen_watches(&mut self) {
+        self.screen_watches.clear();
+        self.screen_watch_hit = None;
+    }
+
+    /// the instruction that triggered a screen watch, kept until `resume`
+    pub fn screen_watch_hit(&self) -> Option<ScreenWatchHit> {
+        self.screen_watch_hit
+    }
+
+    /// report every executed instruction to `sink`, with the registers it changed.
+    /// instructions that fail aren't reported. not carried over when the machine is cloned
+    pub fn set_trace(&mut self, sink: impl FnMut(TraceEvent) + 'static) {
+        self.trace.set(Some(Box::new(sink)));
+    }
+
+    pub fn clear_trace(&mut self) {
+        self.trace.set(None);
+    }
+
+    /// enable the homebrew debug print opcodes, `0FF1` prints V0..VF and I,
+    /// `0FF2` prints the zero terminated string at I. each print is one call to `output`.
+    /// without an output they are unknown opcodes, like on real interpreters
+    pub fn set_debug_output(&mut self, output: impl FnMut(&str) + 'static) {
+        self.debug_output.set(Some(Box::new(output)));
+    }
+
+    pub fn clear_debug_output(&mut self) {
+        self.debug_output.set(None);
+    }
+
+    /// update the pressed state of hex key `key` (0x0..=0xF)
+    pub fn set_key(&mut self, key: u8, pressed: bool) {
+        self.keys[usize::from(key & 0xF)] = pressed;
+    }
+
+    pub fn keys(&self) -> &[bool; NUM_KEYS] {
+        &self.keys
+    }
+
+    /// the register `FX0A` will store a key into, if the next instruction is blocked on it
+    pub fn waiting_for_key(&self) -> Option<u8> {
+        let op = self.memory.read_u16(self.program_counter);
+        if op & 0xF0FF == 0xF00A {
+            Some(((op & 0x0F00) >> 8) as u8)
+        } else {
+            None
+        }
+    }
+
+    /// advance exactly one frame, `cycles` instructions followed by one timer tick.
+    /// works while paused, so frontends can single-step frames.
+    ///
+    /// within a frame, in order:
+    /// - with `TimerTick::FrameStart` the timers tick first
+    /// - the first instruction clears last frame's collisions and draw calls and
+    ///   samples the keys. whether `EX9E`/`EXA1` see that sample or the keys as last
+    ///   set by `set_key` is up to `SchedulePolicy::input`
+    /// - instructions run one after the other, `CXNN` draws its random byte when
+    ///   it executes. the screen changes as each sprite is drawn, there is no
+    ///   separate commit, so read it after this returns
+    /// - a halt or a breakpoint ends the instructions early, the timers still tick.
+    ///   an error ends the frame right away, without ticking the timers at the end
+    /// - with the default `TimerTick::FrameEnd` the timers tick last
+    ///
+    /// the timers tick once either way, the returned `TimerState` is that tick's
+    pub fn step_frame(&mut self, cycles: u32) -> Result<(ProgramState, TimerState), Chip8Error> {
+        let early_timers = match self.schedule.timers {
+            TimerTick::FrameStart => Some(self.cycle_timers()),
+            TimerTick::FrameEnd => None,
+        };
+        let mut state = ProgramState::Running;
+        for _ in 0..cycles {
+            state = self.cycle()?;
+            if state != ProgramState::Running {
+                break;
+            }
+        }
+        let timers = match early_timers {
+            Some(timers) => timers,
+            None => self.cycle_timers(),
+        };
+
+        if self.paused && state == ProgramState::Running {
+            state = ProgramState::Paused;
+        }
+        Ok((state, timers))
+    }
+
+    /// one frame of a 60Hz frontend loop: `cycles` instructions, then one timer tick.
+    /// unlike `step_frame` this does nothing while paused
+    pub fn run_frame(&mut self, cycles: u32) -> Result<FrameResult, Chip8Error> {
+        if self.paused {
+            return Ok(FrameResult {
+                state: ProgramState::Paused,
+                sound: false,
+                screen_changed: false,
+            });
+        }
+
+        let changes = self.screen.changes();
+        let (state, timers) = self.step_frame(cycles)?;
+        Ok(FrameResult {
+            state,
+            sound: timers == TimerState::PlaySound,
+            screen_changed: self.screen.changes() != changes,
+        })
+    }
+
+    /// time until the delay timer reaches zero, given the pacer driving `tick_timers`
+    #[cfg(feature = "timing")]
+    pub fn delay_timer_remaining(&self, pacer: &FramePacer) -> Duration {
+        Self::timer_remaining(self.delay_timer, pacer)
+    }
+
+    /// time until the sound timer reaches zero, given the pacer driving `tick_timers`
+    #[cfg(feature = "timing")]
+    pub fn sound_timer_remaining(&self, pacer: &FramePacer) -> Duration {
+        Self::timer_remaining(self.sound_timer, pacer)
+    }
+
+    #[cfg(feature = "timing")]
+    fn timer_remaining(value: u8, pacer: &FramePacer) -> Duration {
+        if value == 0 {
+            return Duration::ZERO;
+        }
+        // the next decrement happens at the next frame, the rest a whole frame apart
+        pacer.until_next_frame() + pacer.frame_duration() * u32::from(value - 1)
+    }
+
     fn checked_pc_set<T>(&mut self, val: T) -> Result<(), ()>
     where
         T: Into<usize>,
     {
         self.program_counter = val.into();
-        if self.program_counter > RAM_SIZE - 2 {
+        if self.program_counter > self.memory.size() - 2 {
             self._finished = true;
-            return Err(());
+            Err(())
         } else {
-            return Ok(());
+            Ok(())
         }
     }
 
@@ -132,31 +950,164 @@ impl Chip8 {
         T: Into<usize> + Copy,
     {
         let current_value = self.get_reg(reg);
-        self.set_reg(reg, current_value + val);
+        self.set_reg(reg, current_value.wrapping_add(val));
+    }
+
+    /// write `bytes` from `addr` on for an instruction, honouring write protection
+    fn store(&mut self, addr: usize, bytes: &[u8]) -> Result<(), Chip8Error> {
+        if self.write_protection != WriteProtection::Off {
+            let protected = (0..bytes.len())
+                .map(|offset| (addr + offset) % self.memory.size())
+                .filter(|target| *target < START_ADDR);
+            for target in protected {
+                let violation = ProtectedWrite {
+                    addr: self.program_counter - 2,
+                    target,
+                };
+                if self.write_protection == WriteProtection::Fault {
+                    return Err(Chip8Error::WriteProtected {
+                        addr: violation.addr,
+                        target,
+                    });
+                }
+                self.protected_writes.push(violation);
+            }
+        }
+        self.check_watchpoints(addr, bytes, Access::Write);
+        self.memory.write_range(addr, bytes);
+        Ok(())
+    }
+
+    /// read `len` bytes from `addr` on for an instruction
+    fn load(&mut self, addr: usize, len: usize) -> Vec<u8> {
+        let bytes = self.memory.read_range(addr, len);
+        self.check_watchpoints(addr, &bytes, Access::Read);
+        bytes
+    }
+
+    fn check_watchpoints(&mut self, addr: usize, bytes: &[u8], access: Access) {
+        if self.watchpoints.is_empty() || self.watchpoint_hit.is_some() {
+            return;
+        }
+        for (offset, value) in bytes.iter().enumerate() {
+            let target = (addr + offset) % self.memory.size();
+            let watched = self.watchpoints.iter().any(|watch| {
+                watch.trigger.triggers_on(access)
+                    && (watch.start..watch.start + watch.len).contains(&target)
+            });
+            if watched {
+                self.watchpoint_hit = Some(ProgramState::WatchpointHit {
+                    addr: target,
+                    access,
+                    value: *value,
+                });
+                return;
+            }
+        }
     }
 
-    fn stack_push(&mut self, val: u16) {
+    fn stack_push(&mut self, val: u16) -> Result<(), Chip8Error> {
+        if self.stack_pointer == STACK_SIZE {
+            return Err(Chip8Error::StackOverflow {
+                addr: self.program_counter - 2,
+            });
+        }
         self.stack[self.stack_pointer] = val;
         self.stack_pointer += 1;
+        Ok(())
     }
 
-    fn stack_pop(&mut self) -> u16 {
+    fn stack_pop(&mut self) -> Result<u16, Chip8Error> {
+        if self.stack_pointer == 0 {
+            return Err(Chip8Error::StackUnderflow {
+                addr: self.program_counter - 2,
+            });
+        }
         self.stack_pointer -= 1;
-        self.stack[self.stack_pointer]
+        Ok(self.stack[self.stack_pointer])
     }
 
-    fn exec_op(&mut self, op: u16) {
+    fn exec_op(&mut self, op: u16) -> Result<(), Chip8Error> {
         let nib1 = (op & 0xF000) >> 12;
         let nib2 = (op & 0x0F00) >> 8;
         let nib3 = (op & 0x00F0) >> 4;
         let nib4 = op & 0x000F;
 
         match (nib1, nib2, nib3, nib4) {
-            (0x0, 0x0, 0x0, 0x0) => return,
-            (0x0, 0x0, 0xE, 0x0) => self.screen.reset(),
+            (0x0, 0x0, 0x0, 0x0) => {}
+            (0x0, 0x0, 0xE, 0x0) => self.screen.clear(),
+            (0x0, 0x0, 0xE, 0xD) if self.variant.has_chip8e_opcodes() => {
+                // 00ED: stop
+                self._finished = true;
+                self.exit_requested = true;
+            }
+            (0x0, 0x0, 0xF, 0x2) if self.variant.has_chip8e_opcodes() => {
+                // 00F2: no operation
+            }
+            (0x0, 0x1, 0x5, 0x1) if self.variant.has_chip8e_opcodes() => {
+                // 0151: wait until the delay timer reaches 0
+                if self.delay_timer != 0 {
+                    let _ = self.checked_pc_decrement(2usize);
+                }
+            }
+            (0x0, 0x1, 0x8, 0x8) if self.variant.has_chip8e_opcodes() => {
+                // 0188: skip the next instruction
+                let _ = self.checked_pc_increment(2usize);
+            }
+            (0x0, 0xF, 0xF, 0x1) if self.debug_output.is_enabled() => {
+                // 0FF1: debug print the registers
+                let regs: Vec<String> = self
+                    .v_regs
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, val)| format!("V{idx:X}={val:02X}"))
+                    .collect();
+                let line = format!("{} I={:03X}", regs.join(" "), self.i_reg);
+                self.debug_output.print(&line);
+            }
+            (0x0, 0xF, 0xF, 0x2) if self.debug_output.is_enabled() => {
+                // 0FF2: debug print the zero terminated string at I
+                let text: String = (0..MAX_DEBUG_STRING)
+                    .map(|offset| self.memory.read(usize::from(self.i_reg) + offset))
+                    .take_while(|byte| *byte != 0)
+                    .map(char::from)
+                    .collect();
+                self.debug_output.print(&text);
+            }
+            (0x0, 0x0, 0xC, _) if self.variant.has_schip_opcodes() => {
+                // 00CN: scroll the display down by N pixels
+                self.screen.scroll_down(usize::from(nib4));
+            }
+            (0x0, 0x0, 0xD, _) if self.variant.has_xo_chip_opcodes() => {
+                // 00DN: scroll the display up by N pixels
+                self.screen.scroll_up(usize::from(nib4));
+            }
+            (0x0, 0x0, 0xF, 0xB) if self.variant.has_schip_opcodes() => {
+                // 00FB: scroll the display right by 4 pixels
+                self.screen.scroll_right(4);
+            }
+            (0x0, 0x0, 0xF, 0xC) if self.variant.has_schip_opcodes() => {
+                // 00FC: scroll the display left by 4 pixels
+                self.screen.scroll_left(4);
+            }
+            (0x0, 0x0, 0xF, 0xD) if self.variant.has_schip_opcodes() => {
+                // 00FD: exit the interpreter
+                self._finished = true;
+                self.exit_requested = true;
+            }
+            (0x0, 0x0, 0xF, 0xE) if self.variant.has_schip_opcodes() => {
+                // 00FE: switch to lores (64x32)
+                self.screen
+                    .set_hires(false, self.quirks.clear_on_resolution_switch);
+            }
+            (0x0, 0x0, 0xF, 0xF) if self.variant.has_schip_opcodes() => {
+                // 00FF: switch to hires (128x64)
+                self.screen
+                    .set_hires(true, self.quirks.clear_on_resolution_switch);
+            }
             (0x0, 0x0, 0xE, 0xE) => {
                 // ret
-                let return_addr = self.stack_pop();
+                let return_addr = self.stack_pop()?;
                 let _ = self.checked_pc_set(return_addr);
             }
             (0x1, _, _, _) => {
@@ -169,7 +1120,7 @@ impl Chip8 {
                     self.program_counter
                         .try_into()
                         .expect("program counter cannot be more than memory size"),
-                );
+                )?;
                 let _ = self.checked_pc_set(op & 0xFFF);
             }
             (0x3, _, _, _) => {
@@ -180,6 +1131,31 @@ impl Chip8 {
                 // 4XNN: skip if reg X value != NN
                 self.op_skip_if(nib2, op & 0xFF, false);
             }
+            (0x5, _, _, 0x1) if self.variant.has_chip8e_opcodes() => {
+                // 5XY1: skip if reg X value > reg Y value
+                if self.get_reg(nib2) > self.get_reg(nib3) {
+                    let _ = self.checked_pc_increment(2usize);
+                }
+            }
+            (0x5, _, _, 0x2)
+                if self.variant.has_chip8e_opcodes() || self.variant.has_xo_chip_opcodes() =>
+            {
+                // 5XY2: store registers VX to VY into memory @ I
+                let vals: Vec<u8> = Self::reg_range(nib2, nib3)
+                    .map(|reg| self.get_reg(reg))
+                    .collect();
+                self.store(usize::from(self.i_reg), &vals)?;
+            }
+            (0x5, _, _, 0x3)
+                if self.variant.has_chip8e_opcodes() || self.variant.has_xo_chip_opcodes() =>
+            {
+                // 5XY3: load registers VX to VY from memory @ I
+                let len = Self::reg_range(nib2, nib3).count();
+                let vals = self.load(usize::from(self.i_reg), len);
+                for (reg, val) in Self::reg_range(nib2, nib3).zip(vals) {
+                    self.set_reg(reg, val);
+                }
+            }
             (0x5, _, _, 0x0) => {
                 // 5XY0: skip if reg X value == reg Y value
                 let reg2_val = self.get_reg(nib3);
@@ -208,18 +1184,21 @@ impl Chip8 {
                 let yval = self.get_reg(nib3);
                 let xval = self.get_reg(nib2);
                 self.set_reg(nib2, yval | xval);
+                self.op_logic_vf_reset();
             }
             (0x8, _, _, 0x2) => {
                 // 8XY2: reg X value AND reg Y value, stored in X
                 let yval = self.get_reg(nib3);
                 let xval = self.get_reg(nib2);
                 self.set_reg(nib2, yval & xval);
+                self.op_logic_vf_reset();
             }
             (0x8, _, _, 0x3) => {
                 // 8XY3: reg X value XOR reg Y value, stored in X
                 let yval = self.get_reg(nib3);
                 let xval = self.get_reg(nib2);
                 self.set_reg(nib2, yval ^ xval);
+                self.op_logic_vf_reset();
             }
             (0x8, _, _, 0x4) => {
                 // 8XY4: add reg Y value to reg X
@@ -255,7 +1234,7 @@ impl Chip8 {
             (0x8, _, _, 0x6) => {
                 // 8XY6: shift reg X value by 1 to the right
                 // the flag VF is set to the dropped bit
-                let value = self.get_reg(nib2);
+                let value = self.op_shift_source(nib2, nib3);
 
                 self.set_reg(nib2, value >> 1);
                 self.set_reg(0xFusize, value & 1);
@@ -263,7 +1242,7 @@ impl Chip8 {
             (0x8, _, _, 0xE) => {
                 // 8XYE: shift reg X value by 1 to the left
                 // the flag VF is set to the dropped bit
-                let value = self.get_reg(nib2);
+                let value = self.op_shift_source(nib2, nib3);
 
                 self.set_reg(nib2, value << 1);
                 self.set_reg(0xFusize, (value >> 7) & 1);
@@ -272,72 +1251,100 @@ impl Chip8 {
                 // ANNN: set reg I to NNN
                 self.i_reg = op & 0xFFF;
             }
+            (0xB, 0xB, _, _) if self.variant.has_chip8e_opcodes() => {
+                // BBNN: branch back NN bytes from this instruction
+                let addr = (self.program_counter - 2).wrapping_sub(usize::from(op & 0xFF));
+                let _ = self.checked_pc_set(addr);
+            }
+            (0xB, 0xF, _, _) if self.variant.has_chip8e_opcodes() => {
+                // BFNN: branch forward NN bytes from this instruction
+                let addr = self.program_counter - 2 + usize::from(op & 0xFF);
+                let _ = self.checked_pc_set(addr);
+            }
             (0xB, _, _, _) => {
                 // BNNN: jump to V0 + NNN
-                let addr = u16::from(self.get_reg(0usize)) + (op & 0xFFF);
+                // or BXNN: jump to VX + XNN, with the jump_with_vx quirk
+                let reg = if self.quirks.jump_with_vx { nib2 } else { 0 };
+                let addr = u16::from(self.get_reg(reg)) + (op & 0xFFF);
                 let _ = self.checked_pc_set(addr);
             }
             (0xC, _, _, _) => {
                 // CXNN: set X to random AND NN
-                let r: u8 = rand::random();
+                let r: u8 = self.rng.random();
                 let r2 = r & (op & 0xFF) as u8;
                 self.set_reg(nib2, r2)
             }
+            (0xD, _, _, 0x0) if self.variant.has_schip_opcodes() => {
+                // DXY0: draw a 16x16 sprite at I (2 bytes per row) to coordinates X, Y
+                let row_bytes = if !self.screen.is_hires() && self.quirks.lores_dxy0_8x16 {
+                    1
+                } else {
+                    2
+                };
+                self.op_draw(nib2, nib3, 16, row_bytes);
+            }
             (0xD, _, _, _) => {
                 // DXYN: draw sprite at I with height N to coordinates X, Y
-                let sprite_x = self.get_reg(nib2);
-                let sprite_y = self.get_reg(nib3);
-                let sprite_height = nib4 as u8; // truncate to fit u8, 1 nibble < 8 bits
-
-                let mut pixels_flipped = false;
-                for y_line in 0..sprite_height {
-                    let addr = self.i_reg + u16::from(y_line);
-                    let pixels = self.memory[usize::from(addr)];
-
-                    for x_line in 0..8 {
-                        let current_pixel = (pixels & (0b1000_0000 >> x_line)) != 0;
-
-                        pixels_flipped |= self.screen.set_pixel(
-                            sprite_x + x_line,
-                            sprite_y + y_line,
-                            current_pixel,
-                        );
-                    }
-                }
-
-                if pixels_flipped {
-                    self.set_reg(0xFusize, 1);
-                } else {
-                    self.set_reg(0xFusize, 0);
-                }
+                self.op_draw(nib2, nib3, usize::from(nib4), 1);
             }
             (0xE, _, 0x9, 0xE) => {
                 // EX9E: skip if key id in VX is pressed
                 let vx = self.get_reg(nib2);
-                if self.keys[usize::from(vx)] {
-                    let _ = self.checked_pc_increment(2usize);
+                if self.key_pressed(vx) {
+                    self.skip_next();
                 }
             }
             (0xE, _, 0xA, 0x1) => {
                 // EXA1: skip if key id in VX is NOT pressed
                 let vx = self.get_reg(nib2);
-                if !self.keys[usize::from(vx)] {
-                    let _ = self.checked_pc_increment(2usize);
+                if !self.key_pressed(vx) {
+                    self.skip_next();
                 }
             }
+            (0xF, 0x0, 0x0, 0x0) if self.variant.has_xo_chip_opcodes() => {
+                // F000 NNNN: set I to the 16 bit address in the next word
+                self.i_reg = self.memory.read_u16(self.program_counter);
+                let _ = self.checked_pc_increment(2usize);
+            }
+            (0xF, _, 0x0, 0x1) if self.variant.has_xo_chip_opcodes() => {
+                // FN01: select the planes N (a bitmask) later drawing, clearing and scrolling affect
+                self.screen.select_planes(nib2 as u8);
+            }
+            (0xF, 0x0, 0x0, 0x2) if self.variant.has_xo_chip_opcodes() => {
+                // F002: load the 16 byte audio pattern from memory @ I
+                let pattern = self.load(usize::from(self.i_reg), AUDIO_PATTERN_SIZE);
+                self.audio_pattern.copy_from_slice(&pattern);
+            }
+            (0xF, _, 0x3, 0xA) if self.variant.has_xo_chip_opcodes() => {
+                // FX3A: set the audio pattern playback pitch to VX
+                self.pitch = self.get_reg(nib2);
+            }
             (0xF, _, 0x0, 0xA) => {
                 // FX0A: wait for keypress
-                let mut pressed = false;
-                for (id, is_pressed) in self.keys.iter().enumerate() {
-                    if *is_pressed {
-                        pressed = true;
-                        self.set_reg(nib2, id as u8);
-                        break;
+                // on the vip the key only counts once it's released again
+                let first_pressed = self.keys.iter().position(|pressed| *pressed);
+                let key = if self.quirks.latched_keypad {
+                    match self.key_wait_pressed {
+                        Some(key) if !self.keys[usize::from(key)] => {
+                            self.key_wait_pressed = None;
+                            Some(key)
+                        }
+                        Some(_) => None,
+                        None => {
+                            self.key_wait_pressed = first_pressed.map(|key| key as u8);
+                            None
+                        }
+                    }
+                } else {
+                    first_pressed.map(|key| key as u8)
+                };
+
+                match key {
+                    Some(key) => self.set_reg(nib2, key),
+                    // block execution if not pressed
+                    None => {
+                        let _ = self.checked_pc_decrement(2usize);
                     }
-                }
-                // block execution if not pressed
-                if !pressed {
-                    let _ = self.checked_pc_decrement(2usize);
                 }
             }
             (0xF, _, 0x0, 0x7) => {
@@ -352,44 +1359,850 @@ impl Chip8 {
                 // FX18: set ST to value in VX
                 self.sound_timer = self.get_reg(nib2);
             }
+            (0xF, _, 0x1, 0xB) if self.variant.has_chip8e_opcodes() => {
+                // FX1B: skip VX bytes
+                let skip = self.get_reg(nib2);
+                let _ = self.checked_pc_increment(skip);
+            }
+            (0xF, _, 0x4, 0xF) if self.variant.has_chip8e_opcodes() => {
+                // FX4F: set DT to VX and wait until it reaches 0
+                if !self.delay_wait {
+                    self.delay_timer = self.get_reg(nib2);
+                    self.delay_wait = true;
+                }
+                if self.delay_timer == 0 {
+                    self.delay_wait = false;
+                } else {
+                    let _ = self.checked_pc_decrement(2usize);
+                }
+            }
             (0xF, _, 0x1, 0xE) => {
                 // FX1E: increment I reg with value in VX
                 self.i_reg = self.i_reg.wrapping_add(self.get_reg(nib2).into());
+                if self.quirks.fx1e_overflow_vf {
+                    self.set_reg(0xFusize, u8::from(self.i_reg > 0xFFF));
+                }
             }
             (0xF, _, 0x2, 0x9) => {
-                // FX29: set I to font address of character in vx
-                self.i_reg = u16::from(self.get_reg(nib2)) * 5;
+                // FX29: set I to the font address of the digit in the low nibble of vx
+                let digit = usize::from(self.get_reg(nib2) & 0xF);
+                self.i_reg = (self.font_addr + digit * GLYPH_SIZE) as u16;
+            }
+            (0xF, _, 0x3, 0x0) if self.variant.has_schip_opcodes() => {
+                // FX30: set I to the big (8x10) font address of the digit in vx
+                let digit = usize::from(self.get_reg(nib2) & 0xF);
+                self.i_reg = (self.font_addr + FONTSET_SIZE + digit * BIG_GLYPH_SIZE) as u16;
+            }
+            (0xF, _, 0x7, 0x5) if self.variant.has_schip_opcodes() => {
+                // FX75: store registers V0 to Vx in the rpl user flags
+                let count = usize::from(nib2) + 1;
+                self.rpl_flags[..count].copy_from_slice(&self.v_regs[..count]);
+            }
+            (0xF, _, 0x8, 0x5) if self.variant.has_schip_opcodes() => {
+                // FX85: load registers V0 to Vx from the rpl user flags
+                let count = usize::from(nib2) + 1;
+                self.v_regs[..count].copy_from_slice(&self.rpl_flags[..count]);
             }
             (0xF, _, 0x3, 0x3) => {
                 // FX33: set mem @ [I..I+3) (3 bytes) to binary-coded decimal of value in VX
                 let vx = self.get_reg(nib2);
 
-                self.memory[usize::from(self.i_reg)] = vx / 100; // hundreds
-                self.memory[usize::from(self.i_reg)] = (vx / 10) % 10; // tens
-                self.memory[usize::from(self.i_reg)] = vx % 10; // ones
+                let addr = usize::from(self.i_reg);
+
+                // hundreds, tens, ones
+                self.store(addr, &[vx / 100, (vx / 10) % 10, vx % 10])?;
             }
             (0xF, _, 0x5, 0x5) => {
                 // FX55: store value of registers from V0 to Vx into memory @ I
-                for idx in 0..=nib2 {
-                    self.memory[usize::from(self.i_reg) + usize::from(idx)] =
-                        self.v_regs[usize::from(idx)];
-                }
+                let vals = self.v_regs;
+                self.store(usize::from(self.i_reg), &vals[..=usize::from(nib2)])?;
+                self.op_load_store_increment(nib2);
             }
             (0xF, _, 0x6, 0x5) => {
                 // FX65: load registers V0 to Vx from memory @ I
-                for idx in 0..=nib2 {
-                    self.v_regs[usize::from(idx)] =
-                        self.memory[usize::from(self.i_reg) + usize::from(idx)];
+                let count = usize::from(nib2) + 1;
+                let vals = self.load(usize::from(self.i_reg), count);
+                self.v_regs[..count].copy_from_slice(&vals);
+                self.op_load_store_increment(nib2);
+            }
+            (_, _, _, _) => {
+                return Err(Chip8Error::UnknownOpcode {
+                    addr: self.program_counter - 2,
+                    op,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// draw a sprite at I to the coordinates in reg X and reg Y.
+    /// sprite pixels are XORed onto the screen, VF is set if any pixel was turned off
+    fn op_draw(&mut self, x_reg: u16, y_reg: u16, rows: usize, row_bytes: usize) {
+        let sprite_x = usize::from(self.get_reg(x_reg));
+        let sprite_y = usize::from(self.get_reg(y_reg));
+        // xo-chip draws one sprite per selected plane, stored one after the other
+        let planes: Vec<u8> = (0..NUM_PLANES as u8)
+            .map(|plane| 1 << plane)
+            .filter(|plane| self.screen.selected_planes() & plane != 0)
+            .collect();
+        let sprite_len = rows * row_bytes;
+        let sprite = self.load(usize::from(self.i_reg), sprite_len * planes.len());
+
+        // the start coordinate always wraps, the rest of the sprite wraps or clips
+        let (start_x, start_y) = self.screen.wrap(sprite_x, sprite_y);
+        let clip = self.quirks.clip_sprites;
+
+        let mut pixels_flipped = false;
+        // rows that collided or fell off the bottom of the screen, for schip's VF
+        let mut counted_rows = 0;
+        for (plane, plane_sprite) in planes.iter().zip(sprite.chunks_exact(sprite_len.max(1))) {
+            for (y_line, row) in plane_sprite.chunks_exact(row_bytes).enumerate() {
+                let y = start_y + y_line;
+                let collisions = &mut self.collisions;
+                let row_flipped = self
+                    .screen
+                    .draw_row(*plane, start_x, y, row, clip, |px, py| {
+                        collisions.push((px, py))
+                    });
+
+                pixels_flipped |= row_flipped;
+                if row_flipped || y >= self.screen.height() {
+                    counted_rows += 1;
                 }
             }
-            (_, _, _, _) => unimplemented!(),
+        }
+
+        if self.record_draw_calls {
+            self.draw_calls.push(DrawCall {
+                i: self.i_reg,
+                x: sprite_x as u8,
+                y: sprite_y as u8,
+                width: (row_bytes * 8) as u8,
+                height: rows as u8,
+                collision: pixels_flipped,
+                bytes: sprite,
+            });
+        }
+
+        if self.quirks.vf_row_count && self.screen.is_hires() {
+            self.set_reg(0xFusize, counted_rows);
+        } else if pixels_flipped {
+            self.set_reg(0xFusize, 1);
+        } else {
+            self.set_reg(0xFusize, 0);
+        }
+
+        if pixels_flipped && self.pause_on_collision {
+            self.paused = true;
+        }
+    }
+
+    /// pixels inside each watched region, row by row
+    fn watched_pixels(&self) -> Vec<Vec<u8>> {
+        let (width, height) = (self.screen.width(), self.screen.height());
+        self.screen_watches
+            .iter()
+            .map(|region| {
+                let xs = region.x.min(width)..(region.x + region.width).min(width);
+                let ys = region.y.min(height)..(region.y + region.height).min(height);
+                ys.flat_map(|y| xs.clone().map(move |x| (x, y)))
+                    .map(|(x, y)| self.screen.get_color(x, y))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn check_screen_watches(&mut self, before: &[Vec<u8>], addr: usize, op: u16) {
+        let after = self.watched_pixels();
+        if let Some(watch) = before.iter().zip(&after).position(|(old, new)| old != new) {
+            self.screen_watch_hit = Some(ScreenWatchHit { watch, addr, op });
+            self.paused = true;
+        }
+    }
+
+    /// the vip's FX55/FX65 leave I pointing past the last register transferred
+    fn op_load_store_increment(&mut self, last_reg: u16) {
+        if self.quirks.load_store_increments_i {
+            self.i_reg = self.i_reg.wrapping_add(last_reg + 1);
+        }
+    }
+
+    /// value 8XY6/8XYE shift, the vip shifts VY into VX while schip shifts VX in place
+    fn op_shift_source(&mut self, x_reg: u16, y_reg: u16) -> u8 {
+        if self.quirks.shift_uses_vy {
+            self.get_reg(y_reg)
+        } else {
+            self.get_reg(x_reg)
+        }
+    }
+
+    /// registers X to Y inclusive, counting down if Y < X
+    fn reg_range(x: u16, y: u16) -> impl Iterator<Item = u16> {
+        let (lo, hi) = (x.min(y), x.max(y));
+        let ascending = x <= y;
+        (lo..=hi).map(move |idx| if ascending { idx } else { hi - (idx - lo) })
+    }
+
+    /// key state as seen by EX9E/EXA1, latched once per frame on the vip
+    fn key_pressed(&self, key: u8) -> bool {
+        let key = usize::from(key & 0xF);
+        let latched = match self.schedule.input {
+            InputSampling::FollowQuirks => self.quirks.latched_keypad,
+            InputSampling::FrameStart => true,
+            InputSampling::PerInstruction => false,
+        };
+        if latched {
+            self.latched_keys[key]
+        } else {
+            self.keys[key]
+        }
+    }
+
+    /// the vip's logic ops clobber VF as a side effect
+    fn op_logic_vf_reset(&mut self) {
+        if self.quirks.logic_resets_vf {
+            self.set_reg(0xFusize, 0);
         }
     }
 
     #[inline]
     pub fn op_skip_if(&mut self, v_reg: u16, val: u16, eq: bool) {
         if eq ^ (u16::from(self.v_regs[usize::from(v_reg)]) != val) {
-            self.program_counter += 2;
+            self.skip_next();
         }
     }
+
+    /// step over the next instruction, xo-chip's `F000 NNNN` is 4 bytes long
+    fn skip_next(&mut self) {
+        let long = self.variant.has_xo_chip_opcodes()
+            && self.memory.read_u16(self.program_counter) == 0xF000;
+        let _ = self.checked_pc_increment(if long { 4usize } else { 2 });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::state::WatchTrigger;
+
+    #[test]
+    fn clone_diverges_independently() {
+        let mut original = Chip8::new();
+        original.load_rom(&[0x60, 0x12, 0xA3, 0x00]).unwrap();
+        let mut fork = original.clone();
+
+        fork.tick().unwrap();
+        fork.tick().unwrap();
+        fork.memory.write(0x300, 0xAB);
+        fork.set_key(0x5, true);
+
+        assert_eq!(fork.registers()[0], 0x12);
+        assert_eq!(fork.i_reg(), 0x300);
+        assert_eq!(original.registers(), &[0; NUM_V_REGS]);
+        assert_eq!(original.i_reg(), 0);
+        assert_eq!(original.program_counter(), START_ADDR);
+        assert_eq!(original.memory().read(0x300), 0);
+        assert_eq!(original.keys(), &[false; NUM_KEYS]);
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let mut chip8 = Chip8::new_with_variant(Variant::SuperChip);
+        chip8.set_font_addr(0x50).unwrap();
+        // V0 = 5, I = font digit 5, draw it, scroll right, add 1 to V0, loop
+        let rom = [
+            0x60, 0x05, 0xF0, 0x29, 0xD1, 0x25, 0x00, 0xFB, 0x70, 0x01, 0x12, 0x02,
+        ];
+        chip8.load_rom(&rom).unwrap();
+        chip8.set_key(0x3, true);
+        chip8.step_frame(7).unwrap();
+
+        let snapshot = Snapshot::decode(&chip8.save_state().encode()).unwrap();
+        let mut restored = Chip8::new();
+        restored.load_state(&snapshot).unwrap();
+        assert_eq!(restored.variant(), Variant::SuperChip);
+        assert_eq!(restored.quirks(), Quirks::schip());
+        assert_eq!(restored.font_addr(), 0x50);
+        assert_eq!(restored.save_state(), chip8.save_state());
+
+        chip8.step_frame(20).unwrap();
+        restored.step_frame(20).unwrap();
+        assert_eq!(restored.save_state(), chip8.save_state());
+    }
+
+    #[test]
+    fn snapshot_of_a_halted_machine_stays_halted() {
+        let mut chip8 = Chip8::new_with_variant(Variant::SuperChip);
+        chip8.load_rom(&[0x00, 0xFD]).unwrap();
+        chip8.tick().unwrap();
+        let snapshot = chip8.save_state();
+
+        let mut restored = Chip8::new();
+        restored.load_state(&snapshot).unwrap();
+        assert_eq!(
+            restored.tick(),
+            Ok(ProgramState::Halted {
+                requested_by_rom: true
+            })
+        );
+    }
+
+    #[test]
+    fn random_bytes_replay_after_load_state() {
+        let mut chip8 = Chip8::new();
+        // V0 = random byte, loop
+        chip8.load_rom(&[0xC0, 0xFF, 0x12, 0x00]).unwrap();
+        let draw = |chip8: &mut Chip8| -> Vec<u8> {
+            (0..16)
+                .map(|_| {
+                    chip8.step_frame(2).unwrap();
+                    chip8.registers()[0]
+                })
+                .collect()
+        };
+        draw(&mut chip8);
+        let snapshot = chip8.save_state();
+        let expected = draw(&mut chip8);
+
+        chip8.load_state(&snapshot).unwrap();
+        assert_eq!(draw(&mut chip8), expected);
+        let mut other = Chip8::new();
+        other.load_state(&snapshot).unwrap();
+        assert_eq!(draw(&mut other), expected);
+    }
+
+    #[test]
+    fn variant_machines_get_the_variant_quirks() {
+        let chip8 = Chip8::new_with_variant(Variant::SuperChip);
+        assert_eq!(chip8.variant(), Variant::SuperChip);
+        assert_eq!(chip8.quirks(), Quirks::schip());
+        assert_eq!(
+            Chip8::new_with_variant(Variant::XoChip).quirks(),
+            Quirks::xochip()
+        );
+
+        let chip8 = Chip8::new_with_variant_and_quirks(Variant::SuperChip, Quirks::cosmac_vip());
+        assert_eq!(chip8.variant(), Variant::SuperChip);
+        assert_eq!(chip8.quirks(), Quirks::cosmac_vip());
+    }
+
+    #[test]
+    fn bank_window() {
+        let mut chip8 = Chip8::new();
+        let window = BankWindow {
+            start: 0xE00,
+            len: 0x100,
+            banks: 3,
+        };
+        chip8.set_bank_window(Some(window)).unwrap();
+        // I = E00, V0 = [I], loop
+        chip8
+            .load_rom(&[0xAE, 0x00, 0xF0, 0x65, 0x12, 0x04])
+            .unwrap();
+        chip8.memory.write(0xE00, 0x10);
+        chip8.select_bank(2).unwrap();
+        chip8.memory.write(0xE00, 0x12);
+        chip8.memory.write(0xD00, 0xDD);
+
+        let read = |chip8: &mut Chip8, bank| {
+            chip8.select_bank(bank).unwrap();
+            chip8.program_counter = START_ADDR;
+            chip8.tick().unwrap();
+            chip8.tick().unwrap();
+            chip8.v_regs[0]
+        };
+        assert_eq!(read(&mut chip8, 0), 0x10);
+        assert_eq!(read(&mut chip8, 1), 0x00);
+        assert_eq!(read(&mut chip8, 2), 0x12);
+        assert_eq!(chip8.memory.read(0xD00), 0xDD);
+        assert_eq!(
+            chip8.select_bank(3),
+            Err(Chip8Error::NoSuchBank { bank: 3 })
+        );
+
+        let snapshot = chip8.save_state();
+        let mut restored = Chip8::new();
+        assert!(restored.load_state(&snapshot).is_err());
+        restored.set_bank_window(Some(window)).unwrap();
+        restored.load_state(&snapshot).unwrap();
+        assert_eq!(restored.memory().bank(), 2);
+        assert_eq!(restored.memory().read(0xE00), 0x12);
+    }
+
+    /// where `EX9E` goes when key 0 is pressed between the frame's first
+    /// instruction and it
+    fn pc_after_mid_frame_press(input: InputSampling, latched_keypad: bool) -> usize {
+        let mut chip8 = Chip8::new_with_quirks(Quirks {
+            latched_keypad,
+            ..Quirks::default()
+        });
+        chip8.set_schedule(SchedulePolicy {
+            input,
+            ..SchedulePolicy::default()
+        });
+        // V0 = 0, skip if key V0 is pressed
+        chip8.load_rom(&[0x60, 0x00, 0xE0, 0x9E]).unwrap();
+        chip8.tick().unwrap();
+        chip8.set_key(0, true);
+        chip8.tick().unwrap();
+        chip8.program_counter
+    }
+
+    #[test]
+    fn input_sampling() {
+        let (sampled, live) = (0x204, 0x206);
+        assert_eq!(
+            pc_after_mid_frame_press(InputSampling::FollowQuirks, true),
+            sampled
+        );
+        assert_eq!(
+            pc_after_mid_frame_press(InputSampling::FollowQuirks, false),
+            live
+        );
+        assert_eq!(
+            pc_after_mid_frame_press(InputSampling::FrameStart, false),
+            sampled
+        );
+        assert_eq!(
+            pc_after_mid_frame_press(InputSampling::PerInstruction, true),
+            live
+        );
+    }
+
+    /// the delay timer after a frame that sets it to 5
+    fn delay_after_frame(timers: TimerTick) -> u8 {
+        let mut chip8 = Chip8::new();
+        chip8.set_schedule(SchedulePolicy {
+            timers,
+            ..SchedulePolicy::default()
+        });
+        chip8.delay_timer = 3;
+        // V0 = 5, delay = V0, loop
+        chip8
+            .load_rom(&[0x60, 0x05, 0xF0, 0x15, 0x12, 0x04])
+            .unwrap();
+        chip8.step_frame(3).unwrap();
+        chip8.delay_timer
+    }
+
+    #[test]
+    fn timer_tick() {
+        assert_eq!(delay_after_frame(TimerTick::FrameEnd), 4);
+        assert_eq!(delay_after_frame(TimerTick::FrameStart), 5);
+    }
+
+    /// run `op` with V1 = 0x82 and V2 = 0x03, returning V1 and VF
+    fn shift(op: u16, shift_uses_vy: bool) -> (u8, u8) {
+        let mut chip8 = Chip8::new_with_quirks(Quirks {
+            shift_uses_vy,
+            ..Quirks::default()
+        });
+        chip8.load_rom(&op.to_be_bytes()).unwrap();
+        chip8.v_regs[1] = 0x82;
+        chip8.v_regs[2] = 0x03;
+        chip8.tick().unwrap();
+        (chip8.registers()[1], chip8.registers()[0xF])
+    }
+
+    #[test]
+    fn shift_right_source() {
+        assert_eq!(shift(0x8126, true), (0x01, 1));
+        assert_eq!(shift(0x8126, false), (0x41, 0));
+    }
+
+    #[test]
+    fn shift_left_source() {
+        assert_eq!(shift(0x812E, true), (0x06, 0));
+        assert_eq!(shift(0x812E, false), (0x04, 1));
+    }
+
+    /// the bytes `FX29`/`FX30` point I at for every digit, with the fonts at `font_addr`
+    fn glyphs(font_addr: usize, op: u16, size: usize) -> Vec<Vec<u8>> {
+        let mut chip8 = Chip8::new_with_variant(Variant::SuperChip);
+        chip8.set_font_addr(font_addr).unwrap();
+        chip8.load_rom(&op.to_be_bytes()).unwrap();
+        (0..16u8)
+            .map(|digit| {
+                chip8.program_counter = START_ADDR;
+                // the high nibble is ignored
+                chip8.v_regs[0] = 0xA0 | digit;
+                chip8.tick().unwrap();
+                chip8.memory.read_range(usize::from(chip8.i_reg), size)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn small_font_glyphs() {
+        for font_addr in [FONTSET_ADDR, 0x50] {
+            let expected: Vec<Vec<u8>> = FONTSET.chunks(GLYPH_SIZE).map(<[u8]>::to_vec).collect();
+            assert_eq!(glyphs(font_addr, 0xF029, GLYPH_SIZE), expected);
+        }
+    }
+
+    #[test]
+    fn big_font_glyphs() {
+        for font_addr in [FONTSET_ADDR, 0x50] {
+            let expected: Vec<Vec<u8>> = BIG_FONTSET
+                .chunks(BIG_GLYPH_SIZE)
+                .map(<[u8]>::to_vec)
+                .collect();
+            assert_eq!(glyphs(font_addr, 0xF030, BIG_GLYPH_SIZE), expected);
+        }
+    }
+
+    #[test]
+    fn moving_the_font_clears_the_old_one() {
+        let mut chip8 = Chip8::new();
+        chip8.set_font_addr(0x50).unwrap();
+        assert!(
+            chip8
+                .memory()
+                .read_range(0, 0x50)
+                .iter()
+                .all(|byte| *byte == 0)
+        );
+        assert_eq!(chip8.memory().read_range(0x50, FONTSET_SIZE), FONTSET);
+    }
+
+    /// a `variant` machine with `quirks` after the first `steps` instructions of `rom`
+    fn run(variant: Variant, quirks: Quirks, rom: &[u8], steps: usize) -> Chip8 {
+        let mut chip8 = Chip8::new_with_variant_and_quirks(variant, quirks);
+        chip8.load_rom(rom).unwrap();
+        for _ in 0..steps {
+            chip8.tick().unwrap();
+        }
+        chip8
+    }
+
+    /// I and VF after adding `add` to I = 0xFFF with `FX1E`, VF starts out as 0x55
+    fn fx1e(add: u8, fx1e_overflow_vf: bool) -> (u16, u8) {
+        let quirks = Quirks {
+            fx1e_overflow_vf,
+            ..Quirks::default()
+        };
+        let rom = [0xAF, 0xFF, 0x6F, 0x55, 0x60, add, 0xF0, 0x1E];
+        let chip8 = run(Variant::Chip8, quirks, &rom, 4);
+        (chip8.i_reg(), chip8.registers()[0xF])
+    }
+
+    #[test]
+    fn fx1e_overflow() {
+        assert_eq!(fx1e(1, true), (0x1000, 1));
+        assert_eq!(fx1e(0, true), (0xFFF, 0));
+        assert_eq!(fx1e(1, false), (0x1000, 0x55));
+    }
+
+    /// where `B310` jumps with V0 = 0x02 and V3 = 0x30
+    fn jump(jump_with_vx: bool) -> usize {
+        let quirks = Quirks {
+            jump_with_vx,
+            ..Quirks::default()
+        };
+        let rom = [0x60, 0x02, 0x63, 0x30, 0xB3, 0x10];
+        run(Variant::Chip8, quirks, &rom, 3).program_counter()
+    }
+
+    #[test]
+    fn jump_with_offset() {
+        assert_eq!(jump(false), 0x312);
+        assert_eq!(jump(true), 0x340);
+    }
+
+    /// VF after `op` on V1 = 0x0C and V2 = 0x0A, VF starts out as 0x55
+    fn logic_vf(op: u16, logic_resets_vf: bool) -> u8 {
+        let quirks = Quirks {
+            logic_resets_vf,
+            ..Quirks::default()
+        };
+        let [hi, lo] = op.to_be_bytes();
+        let rom = [0x61, 0x0C, 0x62, 0x0A, 0x6F, 0x55, hi, lo];
+        run(Variant::Chip8, quirks, &rom, 4).registers()[0xF]
+    }
+
+    #[test]
+    fn logic_ops_reset_vf() {
+        for op in [0x8121, 0x8122, 0x8123] {
+            assert_eq!(logic_vf(op, true), 0);
+            assert_eq!(logic_vf(op, false), 0x55);
+        }
+    }
+
+    /// the screen after drawing the 0 glyph at (`x`, 30)
+    fn draw_near_the_edge(x: u8, clip_sprites: bool) -> Screen {
+        let quirks = Quirks {
+            clip_sprites,
+            ..Quirks::default()
+        };
+        let rom = [0x60, 0x00, 0xF0, 0x29, 0x61, x, 0x62, 0x1E, 0xD1, 0x25];
+        run(Variant::Chip8, quirks, &rom, 5).get_screen().clone()
+    }
+
+    #[test]
+    fn clip_sprites() {
+        let clipped = draw_near_the_edge(62, true);
+        assert!(clipped.get_pixel(63usize, 30));
+        assert!(!clipped.get_pixel(0usize, 30));
+        assert!(!clipped.get_pixel(62usize, 0));
+
+        let wrapped = draw_near_the_edge(62, false);
+        assert!(wrapped.get_pixel(63usize, 30));
+        assert!(wrapped.get_pixel(0usize, 30));
+        assert!(wrapped.get_pixel(62usize, 0));
+
+        // the start coordinate wraps either way
+        assert!(draw_near_the_edge(64 + 2, true).get_pixel(2usize, 30));
+    }
+
+    /// I after `op` (X = 2) with I = 0x300
+    fn load_store_i(op: u16, load_store_increments_i: bool) -> u16 {
+        let quirks = Quirks {
+            load_store_increments_i,
+            ..Quirks::default()
+        };
+        let [hi, lo] = op.to_be_bytes();
+        run(Variant::Chip8, quirks, &[0xA3, 0x00, hi, lo], 2).i_reg()
+    }
+
+    #[test]
+    fn load_store_increments_i() {
+        for op in [0xF255, 0xF265] {
+            assert_eq!(load_store_i(op, true), 0x303);
+            assert_eq!(load_store_i(op, false), 0x300);
+        }
+    }
+
+    #[test]
+    fn latched_keypad_waits_for_release() {
+        for latched_keypad in [true, false] {
+            let quirks = Quirks {
+                latched_keypad,
+                ..Quirks::default()
+            };
+            // V0 = key
+            let mut chip8 = run(Variant::Chip8, quirks, &[0xF0, 0x0A], 1);
+            assert_eq!(chip8.program_counter(), START_ADDR);
+
+            chip8.set_key(5, true);
+            chip8.tick().unwrap();
+            let done_on_press = chip8.program_counter() == START_ADDR + 2;
+            assert_eq!(done_on_press, !latched_keypad);
+
+            chip8.set_key(5, false);
+            if !done_on_press {
+                chip8.tick().unwrap();
+            }
+            assert_eq!(chip8.program_counter(), START_ADDR + 2);
+            assert_eq!(chip8.registers()[0], 5);
+        }
+    }
+
+    /// width and height of the box a `DXY0` of a solid sprite lights up
+    fn dxy0(hires: bool, lores_dxy0_8x16: bool) -> (usize, usize) {
+        let mut chip8 = Chip8::new_with_variant_and_quirks(
+            Variant::SuperChip,
+            Quirks {
+                lores_dxy0_8x16,
+                ..Quirks::schip()
+            },
+        );
+        // 00FF in hires, I = 300, draw at 0, 0
+        let mode = if hires { 0xFF } else { 0xE0 };
+        chip8
+            .load_rom(&[0x00, mode, 0xA3, 0x00, 0xD0, 0x00])
+            .unwrap();
+        chip8.memory.write_range(0x300, &[0xFF; 32]);
+        for _ in 0..3 {
+            chip8.tick().unwrap();
+        }
+        let screen = chip8.get_screen();
+        let lit = |x: usize, y: usize| screen.get_pixel(x, y);
+        let width = (0..screen.width()).take_while(|x| lit(*x, 0)).count();
+        let height = (0..screen.height()).take_while(|y| lit(0, *y)).count();
+        let total = screen.iter_screen().filter(|px| *px != 0).count();
+        assert_eq!(total, width * height);
+        (width, height)
+    }
+
+    #[test]
+    fn dxy0_sprite_size() {
+        assert_eq!(dxy0(false, false), (16, 16));
+        assert_eq!(dxy0(false, true), (8, 16));
+        assert_eq!(dxy0(true, false), (16, 16));
+        assert_eq!(dxy0(true, true), (16, 16));
+    }
+
+    /// VF after drawing the 0 glyph at (0, `y`) `times` times
+    fn vf_after_draws(hires: bool, y: u8, times: usize, vf_row_count: bool) -> u8 {
+        let quirks = Quirks {
+            vf_row_count,
+            ..Quirks::schip()
+        };
+        let mode = if hires { 0xFF } else { 0xE0 };
+        let rom = [
+            0x00, mode, 0x60, 0x00, 0xF0, 0x29, 0x61, 0x00, 0x62, y, 0xD1, 0x25, 0xD1, 0x25,
+        ];
+        run(Variant::SuperChip, quirks, &rom, 5 + times).registers()[0xF]
+    }
+
+    #[test]
+    fn vf_row_count() {
+        // all 5 rows collide
+        assert_eq!(vf_after_draws(true, 0, 2, true), 5);
+        assert_eq!(vf_after_draws(true, 0, 2, false), 1);
+        // 3 rows fall off the bottom
+        assert_eq!(vf_after_draws(true, 62, 1, true), 3);
+        assert_eq!(vf_after_draws(true, 62, 1, false), 0);
+        // lores only ever sets 0 or 1
+        assert_eq!(vf_after_draws(false, 0, 2, true), 1);
+    }
+
+    /// V0 = 1, call a subroutine setting V1 = 2, loop
+    const CALL_ROM: [u8; 10] = [0x60, 0x01, 0x22, 0x06, 0x12, 0x04, 0x61, 0x02, 0x00, 0xEE];
+
+    #[test]
+    fn breakpoints() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&CALL_ROM).unwrap();
+        chip8.add_breakpoint(0x206);
+        assert_eq!(chip8.continue_until_break(10), Ok(ProgramState::Paused));
+        assert_eq!(chip8.breakpoint_hit(), Some(0x206));
+        assert_eq!(chip8.program_counter(), 0x206);
+        assert_eq!(chip8.stack(), [0x204]);
+        assert_eq!(chip8.registers()[..2], [1, 0]);
+
+        // the instruction under the breakpoint runs once continued
+        assert_eq!(chip8.step(), Ok(ProgramState::Running));
+        assert_eq!(chip8.breakpoint_hit(), None);
+        assert_eq!(chip8.registers()[1], 2);
+        assert_eq!(chip8.continue_until_break(10), Ok(ProgramState::Running));
+        assert_eq!(chip8.program_counter(), 0x204);
+
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&CALL_ROM).unwrap();
+        assert_eq!(chip8.continue_until_break(10), Ok(ProgramState::Running));
+        assert_eq!(chip8.registers()[1], 2);
+    }
+
+    /// what each of `F055` writing 0x42 to 0x300 and `F065` reading it back return,
+    /// with a watchpoint on `start..start + 1`
+    fn watch(start: usize, trigger: WatchTrigger) -> Vec<ProgramState> {
+        let mut chip8 = Chip8::new();
+        chip8
+            .load_rom(&[0xA3, 0x00, 0x60, 0x42, 0xF0, 0x55, 0xF0, 0x65])
+            .unwrap();
+        chip8.add_watchpoint(Watchpoint {
+            start,
+            len: 1,
+            trigger,
+        });
+        chip8.tick().unwrap();
+        chip8.tick().unwrap();
+        (0..2)
+            .map(|_| {
+                let state = chip8.tick().unwrap();
+                chip8.resume();
+                state
+            })
+            .collect()
+    }
+
+    #[test]
+    fn watchpoints() {
+        let hit = |access| ProgramState::WatchpointHit {
+            addr: 0x300,
+            access,
+            value: 0x42,
+        };
+        let (write, read) = (hit(Access::Write), hit(Access::Read));
+        let running = ProgramState::Running;
+        assert_eq!(watch(0x300, WatchTrigger::Write), [write, running]);
+        assert_eq!(watch(0x300, WatchTrigger::Read), [running, read]);
+        assert_eq!(watch(0x300, WatchTrigger::Either), [write, read]);
+        assert_eq!(watch(0x301, WatchTrigger::Either), [running, running]);
+    }
+
+    #[test]
+    fn trace() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut chip8 = Chip8::new();
+        // V0 = 5, I = 300, V0 += 3, loop
+        chip8
+            .load_rom(&[0x60, 0x05, 0xA3, 0x00, 0x70, 0x03, 0x12, 0x06])
+            .unwrap();
+        let sink = Rc::clone(&events);
+        chip8.set_trace(move |event| sink.borrow_mut().push(event));
+        for _ in 0..4 {
+            chip8.tick().unwrap();
+        }
+
+        let delta = |register, before, after| RegisterDelta {
+            register,
+            before,
+            after,
+        };
+        let expected = [
+            (0x200, 0x6005, 0x202, vec![delta(Register::V(0), 0, 5)]),
+            (0x202, 0xA300, 0x204, vec![delta(Register::I, 0, 0x300)]),
+            (0x204, 0x7003, 0x206, vec![delta(Register::V(0), 5, 8)]),
+            (0x206, 0x1206, 0x206, vec![]),
+        ]
+        .map(|(addr, op, next_pc, deltas)| TraceEvent {
+            instruction: Instruction::decode(addr, op),
+            next_pc,
+            deltas,
+        });
+        assert_eq!(*events.borrow(), expected);
+
+        chip8.clear_trace();
+        chip8.tick().unwrap();
+        assert_eq!(events.borrow().len(), 4);
+    }
+
+    #[test]
+    fn lifecycle_returns_to_running_after_a_key() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        for latched_keypad in [true, false] {
+            let quirks = Quirks {
+                latched_keypad,
+                ..Quirks::default()
+            };
+            // V2 = key, loop
+            let mut chip8 = run(Variant::Chip8, quirks, &[0xF2, 0x0A, 0x12, 0x02], 1);
+            let waiting = Lifecycle::WaitingForInput { register: 2 };
+            assert_eq!(chip8.lifecycle(), waiting);
+            let changes = Rc::new(RefCell::new(Vec::new()));
+            let log = Rc::clone(&changes);
+            chip8.on_lifecycle_change(move |before, after| log.borrow_mut().push((before, after)));
+
+            chip8.set_key(3, true);
+            chip8.tick().unwrap();
+            chip8.set_key(3, false);
+            chip8.tick().unwrap();
+            chip8.tick().unwrap();
+            assert_eq!(chip8.lifecycle(), Lifecycle::Running);
+            assert_eq!(chip8.registers()[2], 3);
+            assert_eq!(*changes.borrow(), [(waiting, Lifecycle::Running)]);
+        }
+    }
+
+    #[test]
+    fn run_frame_reports_screen_changes() {
+        // draw the 0 glyph once, then loop
+        let mut chip8 = Chip8::new();
+        chip8
+            .load_rom(&[0xF0, 0x29, 0xD0, 0x05, 0x12, 0x04])
+            .unwrap();
+        assert!(chip8.run_frame(3).unwrap().screen_changed);
+        assert!(!chip8.run_frame(3).unwrap().screen_changed);
+        // a frontend taking the dirty rect doesn't hide changes from run_frame
+        chip8.take_screen_dirty();
+        chip8.program_counter = START_ADDR;
+        assert!(chip8.run_frame(3).unwrap().screen_changed);
+    }
 }
@@ -0,0 +1,49 @@
+use std::fmt;
+
+/// why the machine couldn't carry on, returned instead of panicking so frontends
+/// can show a diagnostic
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chip8Error {
+    /// no opcode of the current variant matches
+    UnknownOpcode { addr: usize, op: u16 },
+    /// a `2NNN` with all 16 stack slots in use
+    StackOverflow { addr: usize },
+    /// a `00EE` with nothing on the stack
+    StackUnderflow { addr: usize },
+    /// the rom doesn't fit between `START_ADDR` and the end of memory
+    RomTooLarge { size: usize, max: usize },
+    /// an address range reaching past the end of memory
+    MemoryOutOfBounds { addr: usize },
+    /// an instruction at `addr` wrote to `target` below `START_ADDR` with write
+    /// protection set to fault
+    WriteProtected { addr: usize, target: usize },
+    /// a bank the bank window doesn't have
+    NoSuchBank { bank: usize },
+    /// a save state that can't be decoded or doesn't fit this machine
+    InvalidSnapshot(&'static str),
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chip8Error::UnknownOpcode { addr, op } => {
+                write!(f, "unknown opcode {op:04X} at {addr:#05X}")
+            }
+            Chip8Error::StackOverflow { addr } => write!(f, "stack overflow at {addr:#05X}"),
+            Chip8Error::StackUnderflow { addr } => write!(f, "stack underflow at {addr:#05X}"),
+            Chip8Error::RomTooLarge { size, max } => {
+                write!(f, "rom is {size} bytes, at most {max} fit in memory")
+            }
+            Chip8Error::MemoryOutOfBounds { addr } => {
+                write!(f, "address {addr:#X} is outside of memory")
+            }
+            Chip8Error::WriteProtected { addr, target } => {
+                write!(f, "write to protected address {target:#05X} at {addr:#05X}")
+            }
+            Chip8Error::NoSuchBank { bank } => write!(f, "there is no memory bank {bank}"),
+            Chip8Error::InvalidSnapshot(reason) => write!(f, "invalid snapshot: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for Chip8Error {}
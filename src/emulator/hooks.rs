@@ -0,0 +1,169 @@
+use super::instruction::Instruction;
+use super::state::{Lifecycle, TraceEvent};
+
+/// handle returned by `Chip8::add_hook`, used to remove the hook again
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HookId(usize);
+
+struct Hook {
+    id: HookId,
+    /// opcode families (as named by `analysis::opcode_family`) this hook wants,
+    /// `None` for every instruction
+    families: Option<Vec<&'static str>>,
+    callback: Box<dyn FnMut(&Instruction)>,
+}
+
+/// callbacks run right before an instruction executes
+#[derive(Default)]
+pub struct Hooks {
+    hooks: Vec<Hook>,
+    next_id: usize,
+}
+
+/// boxed callbacks can't be cloned, a cloned machine starts without hooks
+impl Clone for Hooks {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    pub fn add(
+        &mut self,
+        families: Option<&[&'static str]>,
+        callback: impl FnMut(&Instruction) + 'static,
+    ) -> HookId {
+        let id = HookId(self.next_id);
+        self.next_id += 1;
+        self.hooks.push(Hook {
+            id,
+            families: families.map(|f| f.to_vec()),
+            callback: Box::new(callback),
+        });
+        id
+    }
+
+    /// returns whether a hook with that id was registered
+    pub fn remove(&mut self, id: HookId) -> bool {
+        let len = self.hooks.len();
+        self.hooks.retain(|hook| hook.id != id);
+        self.hooks.len() != len
+    }
+
+    pub fn run(&mut self, instruction: &Instruction) {
+        let family = instruction.family();
+        for hook in self.hooks.iter_mut() {
+            let wanted = match &hook.families {
+                Some(families) => families.contains(&family),
+                None => true,
+            };
+            if wanted {
+                (hook.callback)(instruction);
+            }
+        }
+    }
+}
+
+type DebugPrint = Box<dyn FnMut(&str)>;
+
+/// where the `0FF1`/`0FF2` debug print opcodes write to, see `Chip8::set_debug_output`
+#[derive(Default)]
+pub struct DebugOutput {
+    output: Option<DebugPrint>,
+}
+
+/// like hooks, a cloned machine starts without a debug output
+impl Clone for DebugOutput {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl DebugOutput {
+    pub fn is_enabled(&self) -> bool {
+        self.output.is_some()
+    }
+
+    pub fn set(&mut self, output: Option<DebugPrint>) {
+        self.output = output;
+    }
+
+    pub fn print(&mut self, line: &str) {
+        if let Some(output) = &mut self.output {
+            output(line);
+        }
+    }
+}
+
+type LifecycleCallback = Box<dyn FnMut(Lifecycle, Lifecycle)>;
+
+/// callbacks run on every `Lifecycle` change, see `Chip8::on_lifecycle_change`
+#[derive(Default)]
+pub struct LifecycleListeners {
+    listeners: Vec<(HookId, LifecycleCallback)>,
+    next_id: usize,
+}
+
+/// like hooks, a cloned machine starts without listeners
+impl Clone for LifecycleListeners {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl LifecycleListeners {
+    pub fn add(&mut self, callback: impl FnMut(Lifecycle, Lifecycle) + 'static) -> HookId {
+        let id = HookId(self.next_id);
+        self.next_id += 1;
+        self.listeners.push((id, Box::new(callback)));
+        id
+    }
+
+    /// returns whether a listener with that id was registered
+    pub fn remove(&mut self, id: HookId) -> bool {
+        let len = self.listeners.len();
+        self.listeners.retain(|(listener, _)| *listener != id);
+        self.listeners.len() != len
+    }
+
+    pub fn notify(&mut self, from: Lifecycle, to: Lifecycle) {
+        for (_, callback) in self.listeners.iter_mut() {
+            callback(from, to);
+        }
+    }
+}
+
+type TraceSink = Box<dyn FnMut(TraceEvent)>;
+
+/// where executed instructions are reported to, see `Chip8::set_trace`
+#[derive(Default)]
+pub struct Trace {
+    sink: Option<TraceSink>,
+}
+
+/// like hooks, a cloned machine starts without a trace
+impl Clone for Trace {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl Trace {
+    pub fn is_enabled(&self) -> bool {
+        self.sink.is_some()
+    }
+
+    pub fn set(&mut self, sink: Option<TraceSink>) {
+        self.sink = sink;
+    }
+
+    pub fn record(&mut self, event: TraceEvent) {
+        if let Some(sink) = &mut self.sink {
+            sink(event);
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// something on the host side that answers reads and writes to a mapped memory
+/// region, see `Chip8::map_host_device`. offsets are relative to the region start
+pub trait HostDevice {
+    fn read(&self, offset: usize) -> u8;
+    fn write(&mut self, offset: usize, val: u8);
+}
+
+/// wall-clock time, unix seconds as 4 big endian bytes. writes are ignored
+pub struct Clock;
+
+impl HostDevice for Clock {
+    fn read(&self, offset: usize) -> u8 {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs() as u32);
+        secs.to_be_bytes().get(offset).copied().unwrap_or(0)
+    }
+
+    fn write(&mut self, _offset: usize, _val: u8) {}
+}
+
+/// output port, every byte written is passed on as a character.
+/// reads return 0
+pub struct CharOutput<F: FnMut(char)> {
+    output: F,
+}
+
+impl<F: FnMut(char)> CharOutput<F> {
+    pub fn new(output: F) -> Self {
+        Self { output }
+    }
+}
+
+impl<F: FnMut(char)> HostDevice for CharOutput<F> {
+    fn read(&self, _offset: usize) -> u8 {
+        0
+    }
+
+    fn write(&mut self, _offset: usize, val: u8) {
+        (self.output)(char::from(val));
+    }
+}
@@ -0,0 +1,32 @@
+use crate::analysis::opcode_family;
+
+/// an opcode split into its operand fields, along with where it was fetched from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub addr: usize,
+    pub op: u16,
+    pub x: u8,
+    pub y: u8,
+    pub n: u8,
+    pub nn: u8,
+    pub nnn: u16,
+}
+
+impl Instruction {
+    pub fn decode(addr: usize, op: u16) -> Self {
+        Self {
+            addr,
+            op,
+            x: ((op & 0x0F00) >> 8) as u8,
+            y: ((op & 0x00F0) >> 4) as u8,
+            n: (op & 0x000F) as u8,
+            nn: (op & 0x00FF) as u8,
+            nnn: op & 0x0FFF,
+        }
+    }
+
+    /// opcode pattern this instruction belongs to, e.g. `"DXYN"`
+    pub fn family(&self) -> &'static str {
+        opcode_family(self.op)
+    }
+}
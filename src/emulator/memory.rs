@@ -0,0 +1,237 @@
+use super::host::HostDevice;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+/// addressable memory sizes the interpreter can be configured with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MemorySize {
+    /// 4KB, the original chip8 / schip address space
+    #[default]
+    Standard,
+    /// 64KB, as used by xo-chip
+    Extended,
+}
+
+impl MemorySize {
+    pub fn bytes(self) -> usize {
+        match self {
+            MemorySize::Standard => 4 * 1024,
+            MemorySize::Extended => 64 * 1024,
+        }
+    }
+}
+
+/// what ram contains before the rom is loaded
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RamInit {
+    /// all 0x00
+    #[default]
+    Zeroes,
+    /// all 0xFF
+    Ones,
+    /// pseudo-random bytes, the same seed always gives the same pattern.
+    /// useful to catch roms reading memory they never wrote
+    Random { seed: u64 },
+}
+
+/// what happens when a program writes below `START_ADDR`, where the interpreter
+/// and the fonts live
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WriteProtection {
+    /// writes go through, like on most interpreters
+    #[default]
+    Off,
+    /// writes go through and are recorded, see `Chip8::protected_writes`
+    Warn,
+    /// the instruction fails with `Chip8Error::WriteProtected` without writing anything
+    Fault,
+}
+
+/// a part of the address space that can show one of several banks of ram,
+/// see `Chip8::set_bank_window`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BankWindow {
+    pub start: usize,
+    pub len: usize,
+    /// including bank 0, the ram the window covers without banking
+    pub banks: usize,
+}
+
+/// the interpreter's ram.
+///
+/// all accesses go through `translate`, which wraps an address as seen by a
+/// program around the address space and then maps it to an offset into the
+/// backing storage, following the selected bank inside the bank window
+pub struct Memory {
+    /// the address space, then the extra banks one after the other
+    data: Box<[u8]>,
+    /// size of the address space
+    size: usize,
+    /// the bank window and the bank it shows
+    banking: Option<(BankWindow, usize)>,
+    /// regions routed to the host instead of ram, none unless asked for
+    devices: Vec<MappedDevice>,
+}
+
+struct MappedDevice {
+    start: usize,
+    len: usize,
+    device: Box<dyn HostDevice>,
+}
+
+/// boxed devices can't be cloned, a cloned memory reads plain ram where they were mapped
+impl Clone for Memory {
+    fn clone(&self) -> Self {
+        Self {
+            data: self.data.clone(),
+            size: self.size,
+            banking: self.banking,
+            devices: Vec::new(),
+        }
+    }
+}
+
+impl Memory {
+    pub fn new(size: MemorySize) -> Self {
+        Self {
+            data: vec![0; size.bytes()].into_boxed_slice(),
+            size: size.bytes(),
+            banking: None,
+            devices: Vec::new(),
+        }
+    }
+
+    /// add the banks of `window` after the address space, or drop them with `None`.
+    /// bank 0 is selected. the window has to lie inside the address space and have
+    /// at least one bank
+    pub fn set_bank_window(&mut self, window: Option<BankWindow>) {
+        let extra = window.map_or(0, |window| (window.banks - 1) * window.len);
+        let mut data = self.data[..self.size].to_vec();
+        data.resize(self.size + extra, 0);
+        self.data = data.into_boxed_slice();
+        self.banking = window.map(|window| (window, 0));
+    }
+
+    pub fn bank_window(&self) -> Option<BankWindow> {
+        self.banking.map(|(window, _)| window)
+    }
+
+    /// the bank the window shows, 0 without a window
+    pub fn bank(&self) -> usize {
+        self.banking.map_or(0, |(_, bank)| bank)
+    }
+
+    /// show `bank` in the window, it has to be below the window's `banks`
+    pub fn select_bank(&mut self, bank: usize) {
+        if let Some((window, selected)) = &mut self.banking {
+            debug_assert!(bank < window.banks);
+            *selected = bank;
+        }
+    }
+
+    /// route reads and writes of `start..start + len` to `device`.
+    /// a later mapping takes precedence where regions overlap
+    pub fn map_device(&mut self, start: usize, len: usize, device: impl HostDevice + 'static) {
+        self.devices.push(MappedDevice {
+            start: self.wrap(start),
+            len,
+            device: Box::new(device),
+        });
+    }
+
+    pub fn unmap_devices(&mut self) {
+        self.devices.clear();
+    }
+
+    fn device_at(&self, idx: usize) -> Option<usize> {
+        self.devices
+            .iter()
+            .rposition(|mapped| (mapped.start..mapped.start + mapped.len).contains(&idx))
+    }
+
+    /// size of the address space in bytes
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn contains(&self, addr: usize) -> bool {
+        addr < self.size()
+    }
+
+    #[inline]
+    fn wrap(&self, addr: usize) -> usize {
+        // sizes are powers of two
+        addr & (self.size - 1)
+    }
+
+    /// offset into `data` of a wrapped address
+    #[inline]
+    fn translate(&self, addr: usize) -> usize {
+        match self.banking {
+            Some((window, bank))
+                if bank > 0 && (window.start..window.start + window.len).contains(&addr) =>
+            {
+                self.size + (bank - 1) * window.len + addr - window.start
+            }
+            _ => addr,
+        }
+    }
+
+    /// mapped devices take precedence over the bank window
+    pub fn read(&self, addr: usize) -> u8 {
+        let addr = self.wrap(addr);
+        if let Some(device) = self.device_at(addr) {
+            let mapped = &self.devices[device];
+            return mapped.device.read(addr - mapped.start);
+        }
+        self.data[self.translate(addr)]
+    }
+
+    pub fn write(&mut self, addr: usize, val: u8) {
+        let addr = self.wrap(addr);
+        if let Some(device) = self.device_at(addr) {
+            let mapped = &mut self.devices[device];
+            mapped.device.write(addr - mapped.start, val);
+            return;
+        }
+        let idx = self.translate(addr);
+        self.data[idx] = val;
+    }
+
+    /// read a big endian word, e.g. an opcode
+    pub fn read_u16(&self, addr: usize) -> u16 {
+        u16::from(self.read(addr)) << 8 | u16::from(self.read(addr + 1))
+    }
+
+    /// copy `len` bytes starting at `addr`, wrapping around the end of memory
+    pub fn read_range(&self, addr: usize, len: usize) -> Vec<u8> {
+        (0..len).map(|offset| self.read(addr + offset)).collect()
+    }
+
+    /// copy `bytes` into memory starting at `addr`, wrapping around the end of memory
+    pub fn write_range(&mut self, addr: usize, bytes: &[u8]) {
+        for (offset, byte) in bytes.iter().enumerate() {
+            self.write(addr + offset, *byte);
+        }
+    }
+
+    /// overwrite all of memory with `init`
+    pub fn fill(&mut self, init: RamInit) {
+        match init {
+            RamInit::Zeroes => self.data.fill(0x00),
+            RamInit::Ones => self.data.fill(0xFF),
+            RamInit::Random { seed } => StdRng::seed_from_u64(seed).fill_bytes(&mut self.data),
+        }
+    }
+
+    /// the backing ram, the address space followed by the extra banks.
+    /// mapped devices are not consulted
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// overwrite the backing ram with `data`, which must be exactly as long as `as_slice`
+    pub(crate) fn restore(&mut self, data: &[u8]) {
+        self.data.copy_from_slice(data);
+    }
+}
@@ -0,0 +1,412 @@
+//! reference table of every opcode the interpreter implements
+
+use super::variant::Variant;
+
+/// documentation for one opcode pattern
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpcodeInfo {
+    /// e.g. `"DXYN"`, hex digits are literal and `X`, `Y`, `N` are operands
+    pub pattern: &'static str,
+    /// assembler style mnemonic, e.g. `"DRW VX, VY, N"`
+    pub mnemonic: &'static str,
+    pub description: &'static str,
+    /// dialects that execute this opcode
+    pub variants: &'static [Variant],
+    /// names of the `Quirks` fields that change what it does
+    pub quirks: &'static [&'static str],
+}
+
+impl OpcodeInfo {
+    /// whether `op` is an instance of this pattern
+    pub fn matches(&self, op: u16) -> bool {
+        self.pattern.chars().enumerate().all(|(idx, c)| {
+            let nibble = (op >> (12 - idx * 4)) & 0xF;
+            match c.to_digit(16) {
+                Some(digit) => u32::from(nibble) == digit,
+                None => true,
+            }
+        })
+    }
+
+    pub fn supported_by(&self, variant: Variant) -> bool {
+        self.variants.contains(&variant)
+    }
+}
+
+const ALL: &[Variant] = &[
+    Variant::Chip8,
+    Variant::SuperChip,
+    Variant::XoChip,
+    Variant::Chip8X,
+    Variant::Chip8E,
+];
+const SCHIP: &[Variant] = &[Variant::SuperChip, Variant::XoChip];
+const XO_CHIP: &[Variant] = &[Variant::XoChip];
+const CHIP_8E: &[Variant] = &[Variant::Chip8E];
+const XO_CHIP_AND_CHIP_8E: &[Variant] = &[Variant::XoChip, Variant::Chip8E];
+
+const fn entry(
+    pattern: &'static str,
+    mnemonic: &'static str,
+    description: &'static str,
+    variants: &'static [Variant],
+    quirks: &'static [&'static str],
+) -> OpcodeInfo {
+    OpcodeInfo {
+        pattern,
+        mnemonic,
+        description,
+        variants,
+        quirks,
+    }
+}
+
+/// all implemented opcodes. more specific patterns come first, so the first
+/// match for a variant is the one that executes
+pub const OPCODES: &[OpcodeInfo] = &[
+    entry("0000", "NOP", "do nothing", ALL, &[]),
+    entry("00E0", "CLS", "clear the screen", ALL, &[]),
+    entry("00EE", "RET", "return from a subroutine", ALL, &[]),
+    entry("00ED", "STOP", "stop the interpreter", CHIP_8E, &[]),
+    entry("00F2", "NOP", "do nothing", CHIP_8E, &[]),
+    entry(
+        "0151",
+        "WAIT DT",
+        "wait until the delay timer reaches 0",
+        CHIP_8E,
+        &[],
+    ),
+    entry("0188", "SKIP", "skip the next instruction", CHIP_8E, &[]),
+    entry(
+        "0FF1",
+        "PRINT REGS",
+        "debug print V0..VF and I, only with a debug output set",
+        ALL,
+        &[],
+    ),
+    entry(
+        "0FF2",
+        "PRINT [I]",
+        "debug print the zero terminated string at I, only with a debug output set",
+        ALL,
+        &[],
+    ),
+    entry(
+        "00CN",
+        "SCD N",
+        "scroll the display down by N pixels",
+        SCHIP,
+        &[],
+    ),
+    entry(
+        "00DN",
+        "SCU N",
+        "scroll the display up by N pixels",
+        XO_CHIP,
+        &[],
+    ),
+    entry(
+        "00FB",
+        "SCR",
+        "scroll the display right by 4 pixels",
+        SCHIP,
+        &[],
+    ),
+    entry(
+        "00FC",
+        "SCL",
+        "scroll the display left by 4 pixels",
+        SCHIP,
+        &[],
+    ),
+    entry("00FD", "EXIT", "exit the interpreter", SCHIP, &[]),
+    entry(
+        "00FE",
+        "LOW",
+        "switch to the 64x32 lores mode",
+        SCHIP,
+        &["clear_on_resolution_switch"],
+    ),
+    entry(
+        "00FF",
+        "HIGH",
+        "switch to the 128x64 hires mode",
+        SCHIP,
+        &["clear_on_resolution_switch"],
+    ),
+    entry("1NNN", "JP NNN", "jump to NNN", ALL, &[]),
+    entry("2NNN", "CALL NNN", "call the subroutine at NNN", ALL, &[]),
+    entry(
+        "3XNN",
+        "SE VX, NN",
+        "skip the next instruction if VX == NN",
+        ALL,
+        &[],
+    ),
+    entry(
+        "4XNN",
+        "SNE VX, NN",
+        "skip the next instruction if VX != NN",
+        ALL,
+        &[],
+    ),
+    entry(
+        "5XY0",
+        "SE VX, VY",
+        "skip the next instruction if VX == VY",
+        ALL,
+        &[],
+    ),
+    entry(
+        "5XY1",
+        "SGT VX, VY",
+        "skip the next instruction if VX > VY",
+        CHIP_8E,
+        &[],
+    ),
+    entry(
+        "5XY2",
+        "STR VX, VY",
+        "store VX..VY into memory at I",
+        XO_CHIP_AND_CHIP_8E,
+        &[],
+    ),
+    entry(
+        "5XY3",
+        "LDR VX, VY",
+        "load VX..VY from memory at I",
+        XO_CHIP_AND_CHIP_8E,
+        &[],
+    ),
+    entry("6XNN", "LD VX, NN", "set VX to NN", ALL, &[]),
+    entry(
+        "7XNN",
+        "ADD VX, NN",
+        "add NN to VX, without carry",
+        ALL,
+        &[],
+    ),
+    entry("8XY0", "LD VX, VY", "set VX to VY", ALL, &[]),
+    entry(
+        "8XY1",
+        "OR VX, VY",
+        "set VX to VX OR VY",
+        ALL,
+        &["logic_resets_vf"],
+    ),
+    entry(
+        "8XY2",
+        "AND VX, VY",
+        "set VX to VX AND VY",
+        ALL,
+        &["logic_resets_vf"],
+    ),
+    entry(
+        "8XY3",
+        "XOR VX, VY",
+        "set VX to VX XOR VY",
+        ALL,
+        &["logic_resets_vf"],
+    ),
+    entry(
+        "8XY4",
+        "ADD VX, VY",
+        "add VY to VX, VF is the carry",
+        ALL,
+        &[],
+    ),
+    entry(
+        "8XY5",
+        "SUB VX, VY",
+        "subtract VY from VX, VF is 1 when there's no borrow",
+        ALL,
+        &[],
+    ),
+    entry(
+        "8XY6",
+        "SHR VX, VY",
+        "shift right by one, VF is the dropped bit",
+        ALL,
+        &["shift_uses_vy"],
+    ),
+    entry(
+        "8XY7",
+        "SUBN VX, VY",
+        "set VX to VY - VX, VF is 1 when there's no borrow",
+        ALL,
+        &[],
+    ),
+    entry(
+        "8XYE",
+        "SHL VX, VY",
+        "shift left by one, VF is the dropped bit",
+        ALL,
+        &["shift_uses_vy"],
+    ),
+    entry(
+        "9XY0",
+        "SNE VX, VY",
+        "skip the next instruction if VX != VY",
+        ALL,
+        &[],
+    ),
+    entry("ANNN", "LD I, NNN", "set I to NNN", ALL, &[]),
+    entry("BBNN", "JB NN", "branch back NN bytes", CHIP_8E, &[]),
+    entry("BFNN", "JF NN", "branch forward NN bytes", CHIP_8E, &[]),
+    entry(
+        "BNNN",
+        "JP V0, NNN",
+        "jump to NNN + V0",
+        ALL,
+        &["jump_with_vx"],
+    ),
+    entry(
+        "CXNN",
+        "RND VX, NN",
+        "set VX to a random byte AND NN",
+        ALL,
+        &[],
+    ),
+    entry(
+        "DXY0",
+        "DRW VX, VY, 0",
+        "draw a 16x16 sprite at I to (VX, VY), VF is set on collision",
+        SCHIP,
+        &["lores_dxy0_8x16", "vf_row_count", "clip_sprites"],
+    ),
+    entry(
+        "DXYN",
+        "DRW VX, VY, N",
+        "draw an 8xN sprite at I to (VX, VY), VF is set on collision",
+        ALL,
+        &["vf_row_count", "clip_sprites"],
+    ),
+    entry(
+        "EX9E",
+        "SKP VX",
+        "skip the next instruction if the key in VX is pressed",
+        ALL,
+        &["latched_keypad"],
+    ),
+    entry(
+        "EXA1",
+        "SKNP VX",
+        "skip the next instruction if the key in VX is not pressed",
+        ALL,
+        &["latched_keypad"],
+    ),
+    entry(
+        "F000",
+        "LD I, NNNN",
+        "set I to the 16 bit address in the next word, a 4 byte instruction",
+        XO_CHIP,
+        &[],
+    ),
+    entry(
+        "FN01",
+        "PLANE N",
+        "select the planes (bitmask N) drawing, clearing and scrolling affect",
+        XO_CHIP,
+        &[],
+    ),
+    entry(
+        "F002",
+        "AUDIO",
+        "load the 16 byte audio pattern from memory at I",
+        XO_CHIP,
+        &[],
+    ),
+    entry("FX07", "LD VX, DT", "set VX to the delay timer", ALL, &[]),
+    entry(
+        "FX0A",
+        "LD VX, K",
+        "wait for a key press and store it in VX",
+        ALL,
+        &["latched_keypad"],
+    ),
+    entry("FX15", "LD DT, VX", "set the delay timer to VX", ALL, &[]),
+    entry("FX18", "LD ST, VX", "set the sound timer to VX", ALL, &[]),
+    entry("FX1B", "SKIP VX", "skip VX bytes", CHIP_8E, &[]),
+    entry(
+        "FX1E",
+        "ADD I, VX",
+        "add VX to I",
+        ALL,
+        &["fx1e_overflow_vf"],
+    ),
+    entry(
+        "FX29",
+        "LD F, VX",
+        "point I at the font sprite for the digit in VX",
+        ALL,
+        &[],
+    ),
+    entry(
+        "FX30",
+        "LD HF, VX",
+        "point I at the big 8x10 font sprite for the digit in VX",
+        SCHIP,
+        &[],
+    ),
+    entry(
+        "FX33",
+        "LD B, VX",
+        "store VX as three decimal digits at I",
+        ALL,
+        &[],
+    ),
+    entry(
+        "FX3A",
+        "PITCH VX",
+        "set the audio pattern playback pitch to VX",
+        XO_CHIP,
+        &[],
+    ),
+    entry(
+        "FX4F",
+        "WAIT VX",
+        "set the delay timer to VX and wait until it reaches 0",
+        CHIP_8E,
+        &[],
+    ),
+    entry(
+        "FX55",
+        "LD [I], VX",
+        "store V0..VX into memory at I",
+        ALL,
+        &["load_store_increments_i"],
+    ),
+    entry(
+        "FX65",
+        "LD VX, [I]",
+        "load V0..VX from memory at I",
+        ALL,
+        &["load_store_increments_i"],
+    ),
+    entry(
+        "FX75",
+        "LD R, VX",
+        "store V0..VX in the rpl user flags",
+        SCHIP,
+        &[],
+    ),
+    entry(
+        "FX85",
+        "LD VX, R",
+        "load V0..VX from the rpl user flags",
+        SCHIP,
+        &[],
+    ),
+];
+
+/// the table entry `op` executes as on `variant`, if it is implemented
+pub fn lookup(op: u16, variant: Variant) -> Option<&'static OpcodeInfo> {
+    OPCODES
+        .iter()
+        .find(|info| info.supported_by(variant) && info.matches(op))
+}
+
+/// all entries for a pattern, e.g. `"5XY1"` (one per meaning across variants)
+pub fn by_pattern(pattern: &str) -> impl Iterator<Item = &'static OpcodeInfo> + '_ {
+    OPCODES.iter().filter(move |info| info.pattern == pattern)
+}
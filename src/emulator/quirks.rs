@@ -0,0 +1,143 @@
+use super::variant::Variant;
+
+/// behaviours that differ between interpreters of the same dialect
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quirks {
+    /// `00FE`/`00FF` clear the screen when switching resolution (octo),
+    /// instead of keeping the picture scaled to the new resolution
+    pub clear_on_resolution_switch: bool,
+    /// `DXY0` draws an 8x16 sprite in lores mode (schip 1.0), instead of 16x16
+    pub lores_dxy0_8x16: bool,
+    /// in hires mode VF is set to the number of sprite rows that collided or
+    /// went off the bottom of the screen (schip 1.1), instead of just 0 or 1
+    pub vf_row_count: bool,
+    /// `FX1E` sets VF to 1 when I goes past 0xFFF and to 0 otherwise (amiga
+    /// interpreter, relied on by spacefight 2091), instead of leaving VF alone
+    pub fx1e_overflow_vf: bool,
+    /// `BNNN` is read as `BXNN` and jumps to XNN + VX (schip), instead of NNN + V0
+    pub jump_with_vx: bool,
+    /// `8XY1`/`8XY2`/`8XY3` reset VF to 0 (cosmac vip), instead of leaving it alone
+    pub logic_resets_vf: bool,
+    /// sprites that cross the right or bottom edge are clipped (cosmac vip, schip),
+    /// instead of wrapping around to the other side. the start coordinate wraps either way
+    pub clip_sprites: bool,
+    /// `FX55`/`FX65` leave I incremented by X + 1 (cosmac vip, needed by e.g.
+    /// animal race), instead of unchanged
+    pub load_store_increments_i: bool,
+    /// `8XY6`/`8XYE` shift VY and store the result in VX (cosmac vip),
+    /// instead of shifting VX in place (schip)
+    pub shift_uses_vy: bool,
+    /// model the vip keypad scan: `EX9E`/`EXA1` see the keys as sampled once at the
+    /// start of each frame and `FX0A` only completes once the pressed key is released,
+    /// instead of sampling the keys instantaneously. `SchedulePolicy::input` can
+    /// choose the sampling for `EX9E`/`EXA1` separately
+    pub latched_keypad: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self {
+            clear_on_resolution_switch: true,
+            lores_dxy0_8x16: false,
+            vf_row_count: false,
+            fx1e_overflow_vf: false,
+            jump_with_vx: false,
+            logic_resets_vf: false,
+            clip_sprites: false,
+            load_store_increments_i: false,
+            shift_uses_vy: false,
+            latched_keypad: false,
+        }
+    }
+}
+
+impl Quirks {
+    /// the original cosmac vip interpreter
+    pub fn cosmac_vip() -> Self {
+        Self {
+            logic_resets_vf: true,
+            clip_sprites: true,
+            load_store_increments_i: true,
+            shift_uses_vy: true,
+            latched_keypad: true,
+            ..Self::default()
+        }
+    }
+
+    /// schip 1.1 on the hp48
+    pub fn schip() -> Self {
+        Self {
+            clear_on_resolution_switch: false,
+            vf_row_count: true,
+            jump_with_vx: true,
+            clip_sprites: true,
+            ..Self::default()
+        }
+    }
+
+    /// xo-chip as implemented by octo
+    pub fn xochip() -> Self {
+        Self {
+            load_store_increments_i: true,
+            shift_uses_vy: true,
+            ..Self::default()
+        }
+    }
+
+    /// the preset matching what roms for `variant` usually expect
+    pub fn for_variant(variant: Variant) -> Self {
+        match variant {
+            Variant::Chip8 | Variant::Chip8X | Variant::Chip8E => Self::cosmac_vip(),
+            Variant::SuperChip => Self::schip(),
+            Variant::XoChip => Self::xochip(),
+        }
+    }
+
+    /// field names, as accepted by `get` and `set`
+    pub const NAMES: &'static [&'static str] = &[
+        "clear_on_resolution_switch",
+        "lores_dxy0_8x16",
+        "vf_row_count",
+        "fx1e_overflow_vf",
+        "jump_with_vx",
+        "logic_resets_vf",
+        "clip_sprites",
+        "load_store_increments_i",
+        "shift_uses_vy",
+        "latched_keypad",
+    ];
+
+    /// look a quirk up by field name, for command lines and config files
+    pub fn get(&self, name: &str) -> Option<bool> {
+        let mut copy = *self;
+        copy.field_mut(name).map(|field| *field)
+    }
+
+    /// set a quirk by field name, returns false if there is no such quirk
+    pub fn set(&mut self, name: &str, enabled: bool) -> bool {
+        match self.field_mut(name) {
+            Some(field) => {
+                *field = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn field_mut(&mut self, name: &str) -> Option<&mut bool> {
+        Some(match name {
+            "clear_on_resolution_switch" => &mut self.clear_on_resolution_switch,
+            "lores_dxy0_8x16" => &mut self.lores_dxy0_8x16,
+            "vf_row_count" => &mut self.vf_row_count,
+            "fx1e_overflow_vf" => &mut self.fx1e_overflow_vf,
+            "jump_with_vx" => &mut self.jump_with_vx,
+            "logic_resets_vf" => &mut self.logic_resets_vf,
+            "clip_sprites" => &mut self.clip_sprites,
+            "load_store_increments_i" => &mut self.load_store_increments_i,
+            "shift_uses_vy" => &mut self.shift_uses_vy,
+            "latched_keypad" => &mut self.latched_keypad,
+            _ => return None,
+        })
+    }
+}
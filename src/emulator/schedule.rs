@@ -0,0 +1,35 @@
+//! when things happen within a frame where interpreters disagree, see
+//! `Chip8::step_frame` for the full order
+
+/// when `EX9E`/`EXA1` look at the keypad
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InputSampling {
+    /// at frame start with the `latched_keypad` quirk, per instruction without it
+    #[default]
+    FollowQuirks,
+    /// once, when the frame's first instruction runs (cosmac vip)
+    FrameStart,
+    /// every instruction sees the keys as last set by `set_key`
+    PerInstruction,
+}
+
+/// when the delay and sound timers count down
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimerTick {
+    /// after the frame's instructions
+    #[default]
+    FrameEnd,
+    /// before the frame's instructions, like an interrupt at the start of vblank
+    FrameStart,
+}
+
+/// the per-frame schedule `Chip8::step_frame` and `Chip8::run_frame` follow,
+/// set with `Chip8::set_schedule`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SchedulePolicy {
+    pub input: InputSampling,
+    pub timers: TimerTick,
+}
@@ -0,0 +1,268 @@
+//! save states: everything a program can observe, so loading one resumes
+//! exactly where it was taken
+
+use super::error::Chip8Error;
+use super::quirks::Quirks;
+use super::schedule::{InputSampling, SchedulePolicy, TimerTick};
+use super::variant::Variant;
+
+/// first bytes of an encoded snapshot
+const MAGIC: &[u8; 4] = b"C8SN";
+/// bumped whenever the encoding changes, older versions are rejected
+pub const SNAPSHOT_VERSION: u16 = 6;
+/// the chacha word position is a 68 bit counter
+const MAX_RNG_WORD_POS: u128 = 1 << 68;
+
+/// the machine state captured by `Chip8::save_state`.
+///
+/// debugging aids (hooks, watches, breakpoints) and mapped host devices are
+/// not part of it
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snapshot {
+    pub(super) program_counter: u32,
+    pub(super) i_reg: u16,
+    pub(super) v_regs: [u8; 16],
+    pub(super) stack: [u16; 16],
+    pub(super) stack_pointer: u8,
+    pub(super) delay_timer: u8,
+    pub(super) sound_timer: u8,
+    pub(super) keys: [bool; 16],
+    /// what `EX9E`/`EXA1` see with the latched keypad quirk
+    pub(super) latched_keys: [bool; 16],
+    /// the key a latched `FX0A` is waiting to be released
+    pub(super) key_wait_pressed: Option<u8>,
+    /// a chip-8e `FX4F` is waiting for the delay timer
+    pub(super) delay_wait: bool,
+    /// the machine halted, and whether the rom asked for it with `00FD`
+    pub(super) finished: bool,
+    pub(super) exit_requested: bool,
+    pub(super) variant: Variant,
+    pub(super) quirks: Quirks,
+    pub(super) schedule: SchedulePolicy,
+    pub(super) font_addr: u16,
+    pub(super) rpl_flags: [u8; 16],
+    pub(super) audio_pattern: [u8; 16],
+    pub(super) pitch: u8,
+    pub(super) hires: bool,
+    pub(super) planes: u8,
+    /// `CXNN`'s generator, its seed and how many 32 bit words it has produced,
+    /// so random bytes replay too
+    pub(super) rng_seed: [u8; 32],
+    pub(super) rng_word_pos: u128,
+    /// the bank the bank window shows, `memory` holds every bank
+    pub(super) bank: u16,
+    pub(super) memory: Vec<u8>,
+    /// color index of every cell, see `Screen::get_color`
+    pub(super) screen: Vec<u8>,
+}
+
+impl Snapshot {
+    /// the stable binary form: a magic number and version, then every field big endian
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(128 + self.memory.len() + self.screen.len());
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&SNAPSHOT_VERSION.to_be_bytes());
+        out.extend_from_slice(&self.program_counter.to_be_bytes());
+        out.extend_from_slice(&self.i_reg.to_be_bytes());
+        out.extend_from_slice(&self.v_regs);
+        for addr in self.stack {
+            out.extend_from_slice(&addr.to_be_bytes());
+        }
+        out.extend_from_slice(&[self.stack_pointer, self.delay_timer, self.sound_timer]);
+        out.extend(self.keys.iter().map(|pressed| u8::from(*pressed)));
+        out.extend(self.latched_keys.iter().map(|pressed| u8::from(*pressed)));
+        out.extend_from_slice(&[
+            // 0xFF is no key, keys are 0..16
+            self.key_wait_pressed.unwrap_or(0xFF),
+            u8::from(self.delay_wait),
+            u8::from(self.finished),
+            u8::from(self.exit_requested),
+            Variant::ALL
+                .iter()
+                .position(|variant| *variant == self.variant)
+                .expect("every variant is in ALL") as u8,
+        ]);
+        let quirks = Quirks::NAMES
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| self.quirks.get(name) == Some(true))
+            .fold(0u16, |bits, (idx, _)| bits | 1 << idx);
+        out.extend_from_slice(&quirks.to_be_bytes());
+        out.extend_from_slice(&[
+            match self.schedule.input {
+                InputSampling::FollowQuirks => 0,
+                InputSampling::FrameStart => 1,
+                InputSampling::PerInstruction => 2,
+            },
+            match self.schedule.timers {
+                TimerTick::FrameEnd => 0,
+                TimerTick::FrameStart => 1,
+            },
+        ]);
+        out.extend_from_slice(&self.font_addr.to_be_bytes());
+        out.extend_from_slice(&self.rpl_flags);
+        out.extend_from_slice(&self.audio_pattern);
+        out.extend_from_slice(&[self.pitch, u8::from(self.hires), self.planes]);
+        out.extend_from_slice(&self.rng_seed);
+        out.extend_from_slice(&self.rng_word_pos.to_be_bytes());
+        out.extend_from_slice(&self.bank.to_be_bytes());
+        for bytes in [&self.memory, &self.screen] {
+            out.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+            out.extend_from_slice(bytes);
+        }
+        out
+    }
+
+    /// parse the output of `encode`
+    pub fn decode(bytes: &[u8]) -> Result<Self, Chip8Error> {
+        let mut reader = Reader { bytes };
+        if reader.take(4)? != MAGIC {
+            return Err(Chip8Error::InvalidSnapshot("not a snapshot"));
+        }
+        if reader.u16()? != SNAPSHOT_VERSION {
+            return Err(Chip8Error::InvalidSnapshot("unsupported snapshot version"));
+        }
+
+        let program_counter = reader.u32()?;
+        let i_reg = reader.u16()?;
+        let v_regs = reader.array()?;
+        let mut stack = [0; 16];
+        for addr in stack.iter_mut() {
+            *addr = reader.u16()?;
+        }
+        let [stack_pointer, delay_timer, sound_timer] = reader.array()?;
+        let keys = reader.array::<16>()?.map(|pressed| pressed != 0);
+        let latched_keys = reader.array::<16>()?.map(|pressed| pressed != 0);
+        let [
+            key_wait_pressed,
+            delay_wait,
+            finished,
+            exit_requested,
+            variant,
+        ] = reader.array()?;
+        let key_wait_pressed = match key_wait_pressed {
+            0xFF => None,
+            key @ 0..16 => Some(key),
+            _ => {
+                return Err(Chip8Error::InvalidSnapshot(
+                    "waiting for a key that doesn't exist",
+                ));
+            }
+        };
+        let variant = *Variant::ALL
+            .get(usize::from(variant))
+            .ok_or(Chip8Error::InvalidSnapshot("unknown variant"))?;
+        let quirk_bits = reader.u16()?;
+        let mut quirks = Quirks::default();
+        for (idx, name) in Quirks::NAMES.iter().enumerate() {
+            quirks.set(name, quirk_bits & 1 << idx != 0);
+        }
+        let [input, timers] = reader.array()?;
+        let schedule = SchedulePolicy {
+            input: match input {
+                0 => InputSampling::FollowQuirks,
+                1 => InputSampling::FrameStart,
+                2 => InputSampling::PerInstruction,
+                _ => return Err(Chip8Error::InvalidSnapshot("unknown input sampling")),
+            },
+            timers: match timers {
+                0 => TimerTick::FrameEnd,
+                1 => TimerTick::FrameStart,
+                _ => return Err(Chip8Error::InvalidSnapshot("unknown timer tick")),
+            },
+        };
+        let font_addr = reader.u16()?;
+        let rpl_flags = reader.array()?;
+        let audio_pattern = reader.array()?;
+        let [pitch, hires, planes] = reader.array()?;
+        let rng_seed = reader.array()?;
+        let rng_word_pos = u128::from_be_bytes(reader.array()?);
+        if rng_word_pos >= MAX_RNG_WORD_POS {
+            return Err(Chip8Error::InvalidSnapshot("rng position out of range"));
+        }
+        let bank = reader.u16()?;
+        let memory_len = reader.u32()? as usize;
+        let memory = reader.take(memory_len)?.to_vec();
+        let screen_len = reader.u32()? as usize;
+        let screen = reader.take(screen_len)?.to_vec();
+        if !reader.bytes.is_empty() {
+            return Err(Chip8Error::InvalidSnapshot(
+                "trailing bytes after the snapshot",
+            ));
+        }
+
+        Ok(Self {
+            program_counter,
+            i_reg,
+            v_regs,
+            stack,
+            stack_pointer,
+            delay_timer,
+            sound_timer,
+            keys,
+            latched_keys,
+            key_wait_pressed,
+            delay_wait: delay_wait != 0,
+            finished: finished != 0,
+            exit_requested: exit_requested != 0,
+            variant,
+            quirks,
+            schedule,
+            font_addr,
+            rpl_flags,
+            audio_pattern,
+            pitch,
+            hires: hires != 0,
+            planes,
+            rng_seed,
+            rng_word_pos,
+            bank,
+            memory,
+            screen,
+        })
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Chip8Error> {
+        if self.bytes.len() < len {
+            return Err(Chip8Error::InvalidSnapshot("snapshot is truncated"));
+        }
+        let (head, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], Chip8Error> {
+        Ok(self.take(N)?.try_into().expect("took exactly N bytes"))
+    }
+
+    fn u16(&mut self) -> Result<u16, Chip8Error> {
+        Ok(u16::from_be_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, Chip8Error> {
+        Ok(u32::from_be_bytes(self.array()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::core::Chip8;
+
+    #[test]
+    fn rejects_rng_position_out_of_range() {
+        let mut snapshot = Chip8::new().save_state();
+        snapshot.rng_word_pos = MAX_RNG_WORD_POS;
+        assert_eq!(
+            Snapshot::decode(&snapshot.encode()),
+            Err(Chip8Error::InvalidSnapshot("rng position out of range"))
+        );
+    }
+}
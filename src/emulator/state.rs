@@ -1,60 +1,518 @@
-use super::core::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use super::core::{HIRES_SCREEN_HEIGHT, HIRES_SCREEN_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH};
+use super::error::Chip8Error;
+use super::instruction::Instruction;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ProgramState {
     Running,
-    // WaitingForInput,
-    Finished,
-    // Paused,
+    /// the machine stopped, either because the rom executed `00FD` or
+    /// because the program counter ran off the end of memory
+    Halted {
+        requested_by_rom: bool,
+    },
+    Paused,
+    /// an instruction accessed memory inside a watchpoint, the machine paused after it
+    WatchpointHit {
+        /// address accessed
+        addr: usize,
+        access: Access,
+        /// byte read or written
+        value: u8,
+    },
 }
 
+/// a data access an instruction made to memory
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// accesses a watchpoint stops on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchTrigger {
+    Read,
+    Write,
+    Either,
+}
+
+impl WatchTrigger {
+    pub fn triggers_on(self, access: Access) -> bool {
+        matches!(
+            (self, access),
+            (WatchTrigger::Either, _)
+                | (WatchTrigger::Read, Access::Read)
+                | (WatchTrigger::Write, Access::Write)
+        )
+    }
+}
+
+/// `len` bytes of memory from `start` on to watch, see `Chip8::add_watchpoint`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub start: usize,
+    pub len: usize,
+    pub trigger: WatchTrigger,
+}
+
+/// where the machine is in its life, see `Chip8::lifecycle`. unlike `ProgramState`,
+/// which describes a single call, this is what a frontend's ui should follow.
+///
+/// transitions:
+/// - `Idle` -> `Running` when a rom or a snapshot is loaded, or straight to
+///   `WaitingForInput` if it starts with an `FX0A`
+/// - `Running` <-> `WaitingForInput` as an `FX0A` starts and stops blocking
+/// - `Running` or `WaitingForInput` -> `Paused` on `pause`, a breakpoint, a watchpoint,
+///   a screen watch or a collision with pause on collision enabled, and back on `resume`
+/// - -> `Halted` when the rom executes `00FD` or runs off the end of memory
+/// - -> `Faulted` when an instruction fails, and back once an instruction executes
+///   again (e.g. after the frontend fixed memory up)
+/// - `Halted` or `Faulted` -> `Running` when a snapshot is loaded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lifecycle {
+    /// nothing loaded yet
+    Idle,
+    Running,
+    /// blocked on `FX0A`, which stores the key into V`register`
+    WaitingForInput {
+        register: u8,
+    },
+    Paused,
+    Halted {
+        requested_by_rom: bool,
+    },
+    Faulted(Chip8Error),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TimerState {
     PlaySound,
     None,
 }
 
+/// what a frontend needs to know after `Chip8::run_frame`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameResult {
+    pub state: ProgramState,
+    /// the beeper should be on for this frame
+    pub sound: bool,
+    /// something was drawn, cleared or scrolled during the frame, so it needs redrawing
+    pub screen_changed: bool,
+}
+
+/// a single `DXYN` (or `DXY0`) as executed, for the per-frame draw log
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DrawCall {
+    pub i: u16,
+    pub x: u8,
+    pub y: u8,
+    /// 8, or 16 for schip `DXY0`
+    pub width: u8,
+    pub height: u8,
+    pub collision: bool,
+    /// sprite rows as read from memory @ I
+    pub bytes: Vec<u8>,
+}
+
+/// a register an instruction can change
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Register {
+    /// V0..VF
+    V(u8),
+    I,
+}
+
+/// a register that changed value, see `TraceEvent`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RegisterDelta {
+    pub register: Register,
+    pub before: u16,
+    pub after: u16,
+}
+
+/// one executed instruction, as passed to the `Chip8::set_trace` sink
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceEvent {
+    /// the fetched opcode and where from, decoded
+    pub instruction: Instruction,
+    /// program counter after the instruction
+    pub next_pc: usize,
+    /// registers it changed, V0..VF in order, then I
+    pub deltas: Vec<RegisterDelta>,
+}
+
+/// a write below `START_ADDR` let through by `WriteProtection::Warn`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProtectedWrite {
+    /// address of the instruction
+    pub addr: usize,
+    /// address written to
+    pub target: usize,
+}
+
+/// a rectangle of pixels, in the coordinates of the current resolution
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScreenRegion {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// the instruction that changed a pixel inside a watched region
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScreenWatchHit {
+    /// index of the region, in the order they were added
+    pub watch: usize,
+    pub addr: usize,
+    pub op: u16,
+}
+
+/// xo-chip draws on up to two bit planes, a pixel's color index has bit 0 set
+/// when it is lit on the first plane and bit 1 on the second
+pub const NUM_PLANES: usize = 2;
+const ALL_PLANES: u8 = 0b11;
+
+#[derive(Clone)]
 pub struct Screen {
-    inner: [bool; SCREEN_WIDTH * SCREEN_HEIGHT],
+    // sized for hires, in lores mode only the first SCREEN_WIDTH * SCREEN_HEIGHT cells are used.
+    // each cell is a color index, one bit per plane
+    inner: [u8; HIRES_SCREEN_WIDTH * HIRES_SCREEN_HEIGHT],
+    hires: bool,
+    /// planes that drawing, clearing and scrolling affect, chosen by xo-chip `FN01`
+    planes: u8,
+    /// bounding box of the pixels changed since the last `take_dirty`
+    dirty: Option<ScreenRegion>,
+    /// bumped along with every change to `dirty`, so the machine can tell a frame
+    /// drew something without taking the frontend's dirty rect
+    changes: u64,
+}
+
+/// screens are equal when they show the same picture, dirty tracking doesn't count
+impl PartialEq for Screen {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner && self.hires == other.hires && self.planes == other.planes
+    }
+}
+
+impl Eq for Screen {}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Screen {
     pub fn new() -> Self {
         Self {
-            inner: [false; SCREEN_WIDTH * SCREEN_HEIGHT],
+            inner: [0; HIRES_SCREEN_WIDTH * HIRES_SCREEN_HEIGHT],
+            hires: false,
+            planes: 0b01,
+            dirty: Some(ScreenRegion {
+                x: 0,
+                y: 0,
+                width: SCREEN_WIDTH,
+                height: SCREEN_HEIGHT,
+            }),
+            changes: 0,
         }
     }
 
+    /// blank every plane
     pub fn reset(&mut self) {
-        self.inner.fill(false);
+        self.inner.fill(0);
+        self.mark_all_dirty();
+    }
+
+    /// blank the selected planes, what `00E0` does
+    pub fn clear(&mut self) {
+        let keep = !self.planes;
+        self.inner.iter_mut().for_each(|cell| *cell &= keep);
+        self.mark_all_dirty();
+    }
+
+    /// the area changed since the last call, `None` if nothing was drawn.
+    /// a new screen, a clear, scroll or resolution switch mark the whole screen.
+    /// lets frontends skip redrawing and texture uploads on unchanged frames
+    pub fn take_dirty(&mut self) -> Option<ScreenRegion> {
+        self.dirty.take()
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.is_some()
+    }
+
+    /// a count that changes whenever the screen is marked dirty
+    pub(crate) fn changes(&self) -> u64 {
+        self.changes
+    }
+
+    fn mark_all_dirty(&mut self) {
+        self.changes += 1;
+        // replaces rather than grows the box, which may be in the old resolution's coordinates
+        self.dirty = Some(ScreenRegion {
+            x: 0,
+            y: 0,
+            width: self.width(),
+            height: self.height(),
+        });
+    }
+
+    fn mark_dirty(&mut self, x: usize, y: usize) {
+        self.changes += 1;
+        self.dirty = Some(match self.dirty {
+            None => ScreenRegion {
+                x,
+                y,
+                width: 1,
+                height: 1,
+            },
+            Some(rect) => {
+                let left = rect.x.min(x);
+                let top = rect.y.min(y);
+                ScreenRegion {
+                    x: left,
+                    y: top,
+                    width: (rect.x + rect.width).max(x + 1) - left,
+                    height: (rect.y + rect.height).max(y + 1) - top,
+                }
+            }
+        });
+    }
+
+    /// bitmask of the planes later operations affect, only the low `NUM_PLANES` bits count
+    pub fn select_planes(&mut self, planes: u8) {
+        self.planes = planes & ALL_PLANES;
+    }
+
+    pub fn selected_planes(&self) -> u8 {
+        self.planes
+    }
+
+    pub fn width(&self) -> usize {
+        if self.hires {
+            HIRES_SCREEN_WIDTH
+        } else {
+            SCREEN_WIDTH
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        if self.hires {
+            HIRES_SCREEN_HEIGHT
+        } else {
+            SCREEN_HEIGHT
+        }
+    }
+
+    pub fn is_hires(&self) -> bool {
+        self.hires
+    }
+
+    /// every cell's color index, including the ones unused in lores
+    pub(crate) fn cells(&self) -> &[u8] {
+        &self.inner
+    }
+
+    /// put back what `cells`, `is_hires` and `selected_planes` returned.
+    /// `cells` must be as long as the buffer
+    pub(crate) fn restore(&mut self, cells: &[u8], hires: bool, planes: u8) {
+        self.inner.copy_from_slice(cells);
+        self.hires = hires;
+        self.planes = planes & ALL_PLANES;
+        self.mark_all_dirty();
     }
 
-    fn coordinate_to_index<T>(x: T, y: T) -> usize
+    /// switch between 64x32 and 128x64.
+    /// with `clear` unset the picture is kept by scaling it to the new resolution,
+    /// so a frontend never sees the old buffer reinterpreted at the wrong width
+    pub fn set_hires(&mut self, hires: bool, clear: bool) {
+        if hires == self.hires {
+            if clear {
+                self.reset();
+            }
+            return;
+        }
+        // reset below marks the whole screen at the new resolution
+
+        let old = self.inner;
+        let old_width = self.width();
+        self.hires = hires;
+        self.reset();
+        if clear {
+            return;
+        }
+
+        let (width, height) = (self.width(), self.height());
+        for y in 0..height {
+            for x in 0..width {
+                // lores -> hires doubles every pixel, hires -> lores keeps the top left of each 2x2 block
+                let (old_x, old_y) = if hires {
+                    (x / 2, y / 2)
+                } else {
+                    (x * 2, y * 2)
+                };
+                self.inner[y * width + x] = old[old_y * old_width + old_x];
+            }
+        }
+    }
+
+    /// wrap coordinates that fall off the screen back around to the other side
+    pub fn wrap(&self, x: usize, y: usize) -> (usize, usize) {
+        (x % self.width(), y % self.height())
+    }
+
+    fn coordinate_to_index<T>(&self, x: T, y: T) -> usize
     where
         T: Into<usize>,
     {
         // handle overflow
-        let ix = x.into() % SCREEN_WIDTH;
-        let iy = y.into() % SCREEN_HEIGHT;
-        (SCREEN_WIDTH * ix) + iy
+        let (ix, iy) = self.wrap(x.into(), y.into());
+        (self.width() * iy) + ix
     }
 
+    /// whether the pixel is lit on any plane
     pub fn get_pixel<T>(&self, x: T, y: T) -> bool
     where
         T: Into<usize>,
     {
-        self.inner[Screen::coordinate_to_index(x, y)]
+        self.get_color(x, y) != 0
     }
 
+    /// the pixel's color index, bit N is set when it is lit on plane N
+    pub fn get_color<T>(&self, x: T, y: T) -> u8
+    where
+        T: Into<usize>,
+    {
+        self.inner[self.coordinate_to_index(x, y)]
+    }
+
+    /// set the pixel on the selected planes, returns whether it already had that value
     pub fn set_pixel<T>(&mut self, x: T, y: T, val: bool) -> bool
     where
         T: Into<usize>,
     {
-        let idx = Screen::coordinate_to_index(x, y);
-        let res = self.inner[idx] == val;
-        self.inner[idx] = val;
+        let (x, y) = self.wrap(x.into(), y.into());
+        let idx = self.coordinate_to_index(x, y);
+        let before = self.inner[idx];
+        let res = (before & self.planes != 0) == val;
+        if val {
+            self.inner[idx] |= self.planes;
+        } else {
+            self.inner[idx] &= !self.planes;
+        }
+        if self.inner[idx] != before {
+            self.mark_dirty(x, y);
+        }
         res
     }
 
-    pub fn iter_screen() {
-        todo!()
+    /// toggle a pixel on the selected planes, returns true if it was on in any
+    /// of them (i.e. a sprite collision)
+    pub fn flip_pixel<T>(&mut self, x: T, y: T) -> bool
+    where
+        T: Into<usize>,
+    {
+        self.flip_pixel_on(x, y, self.planes)
+    }
+
+    fn flip_pixel_on<T>(&mut self, x: T, y: T, planes: u8) -> bool
+    where
+        T: Into<usize>,
+    {
+        let (x, y) = self.wrap(x.into(), y.into());
+        let idx = self.coordinate_to_index(x, y);
+        let was_on = self.inner[idx] & planes != 0;
+        self.inner[idx] ^= planes;
+        if planes != 0 {
+            self.mark_dirty(x, y);
+        }
+        was_on
+    }
+
+    /// XOR one sprite row (msb is the leftmost pixel) onto `plane` (a single bit of the
+    /// plane mask) starting at (x, y).
+    /// pixels past the right or bottom edge wrap around, or are dropped if `clip` is set.
+    /// `on_collision` is called for every pixel that was turned off, returns whether any was
+    pub fn draw_row(
+        &mut self,
+        plane: u8,
+        x: usize,
+        y: usize,
+        row: &[u8],
+        clip: bool,
+        mut on_collision: impl FnMut(usize, usize),
+    ) -> bool {
+        let (width, height) = (self.width(), self.height());
+        if clip && y >= height {
+            return false;
+        }
+        let y = y % height;
+
+        let mut collided = false;
+        for (byte_idx, pixels) in row.iter().enumerate() {
+            for bit in 0..8 {
+                if pixels & (0b1000_0000 >> bit) == 0 {
+                    continue;
+                }
+
+                let px = x + byte_idx * 8 + bit;
+                if clip && px >= width {
+                    continue;
+                }
+                let px = px % width;
+                if self.flip_pixel_on(px, y, plane) {
+                    collided = true;
+                    on_collision(px, y);
+                }
+            }
+        }
+        collided
+    }
+
+    /// move the selected planes down by `n` rows, rows scrolled in at the top are blank
+    pub fn scroll_down(&mut self, n: usize) {
+        self.scroll_by(0, n as isize);
+    }
+
+    /// move the selected planes up by `n` rows, rows scrolled in at the bottom are blank
+    pub fn scroll_up(&mut self, n: usize) {
+        self.scroll_by(0, -(n as isize));
+    }
+
+    /// move the selected planes right by `n` columns, columns scrolled in on the left are blank
+    pub fn scroll_right(&mut self, n: usize) {
+        self.scroll_by(n as isize, 0);
+    }
+
+    /// move the selected planes left by `n` columns, columns scrolled in on the right are blank
+    pub fn scroll_left(&mut self, n: usize) {
+        self.scroll_by(-(n as isize), 0);
+    }
+
+    fn scroll_by(&mut self, dx: isize, dy: isize) {
+        let (width, height) = (self.width(), self.height());
+        let old = self.inner;
+        for y in 0..height {
+            for x in 0..width {
+                let (src_x, src_y) = (x as isize - dx, y as isize - dy);
+                let in_bounds =
+                    (0..width as isize).contains(&src_x) && (0..height as isize).contains(&src_y);
+                let moved = if in_bounds {
+                    old[src_y as usize * width + src_x as usize] & self.planes
+                } else {
+                    0
+                };
+                let idx = y * width + x;
+                self.inner[idx] = (old[idx] & !self.planes) | moved;
+            }
+        }
+        self.mark_all_dirty();
+    }
+
+    /// color index of every pixel at the current resolution, row by row
+    pub fn iter_screen(&self) -> impl Iterator<Item = u8> + '_ {
+        self.inner[..self.width() * self.height()].iter().copied()
     }
 }
@@ -0,0 +1,55 @@
+use super::memory::MemorySize;
+
+/// the interpreter dialects a rom can be written for
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Variant {
+    #[default]
+    Chip8,
+    SuperChip,
+    XoChip,
+    Chip8X,
+    /// chip-8e, a 1979 extension adding relative branches, extra skips and
+    /// range load/store
+    Chip8E,
+}
+
+impl Variant {
+    pub const ALL: [Variant; 5] = [
+        Variant::Chip8,
+        Variant::SuperChip,
+        Variant::XoChip,
+        Variant::Chip8X,
+        Variant::Chip8E,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::Chip8 => "CHIP-8",
+            Variant::SuperChip => "SUPER-CHIP",
+            Variant::XoChip => "XO-CHIP",
+            Variant::Chip8X => "CHIP-8X",
+            Variant::Chip8E => "CHIP-8E",
+        }
+    }
+
+    /// scrolling, hires and the other schip opcodes
+    pub fn has_schip_opcodes(self) -> bool {
+        matches!(self, Variant::SuperChip | Variant::XoChip)
+    }
+
+    pub fn has_xo_chip_opcodes(self) -> bool {
+        self == Variant::XoChip
+    }
+
+    pub fn has_chip8e_opcodes(self) -> bool {
+        self == Variant::Chip8E
+    }
+
+    pub fn memory_size(self) -> MemorySize {
+        match self {
+            Variant::XoChip => MemorySize::Extended,
+            _ => MemorySize::Standard,
+        }
+    }
+}
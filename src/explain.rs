@@ -0,0 +1,189 @@
+//! plain english descriptions of instructions, for stepping through a rom
+//! while learning how an interpreter works
+
+use crate::emulator::core::Chip8;
+use crate::emulator::instruction::Instruction;
+use crate::emulator::opcodes;
+
+/// what `ins` is about to do, using the operand values currently in `chip8`.
+/// call before executing the instruction
+pub fn explain(ins: &Instruction, chip8: &Chip8) -> String {
+    let regs = chip8.registers();
+    let vx = regs[usize::from(ins.x)];
+    let vy = regs[usize::from(ins.y)];
+    let reg = |idx: u8| format!("V{idx:X} ({})", regs[usize::from(idx)]);
+    let (x, y, n, nn, nnn) = (ins.x, ins.y, ins.n, ins.nn, ins.nnn);
+    let i = chip8.i_reg();
+
+    let Some(info) = opcodes::lookup(ins.op, chip8.variant()) else {
+        return format!(
+            "{:04X} is not a {} instruction",
+            ins.op,
+            chip8.variant().name()
+        );
+    };
+
+    match info.pattern {
+        "00E0" => "clears the screen".to_string(),
+        "00EE" => "returns from a subroutine to the address on top of the stack".to_string(),
+        "00CN" => format!("scrolls the screen down by {n} pixels"),
+        "00DN" => format!("scrolls the screen up by {n} pixels"),
+        "00FB" => "scrolls the screen right by 4 pixels".to_string(),
+        "00FC" => "scrolls the screen left by 4 pixels".to_string(),
+        "00FD" => "exits the interpreter".to_string(),
+        "00FE" => "switches to the 64x32 low resolution mode".to_string(),
+        "00FF" => "switches to the 128x64 high resolution mode".to_string(),
+        "1NNN" => format!("jumps to {nnn:#05X}"),
+        "2NNN" => format!(
+            "calls the subroutine at {nnn:#05X}, pushing the return address {:#05X}",
+            ins.addr.wrapping_add(2)
+        ),
+        "3XNN" => format!("skips the next instruction if {} equals {nn}", reg(x)),
+        "4XNN" => format!("skips the next instruction if {} is not {nn}", reg(x)),
+        "5XY0" => format!("skips the next instruction if {} equals {}", reg(x), reg(y)),
+        "9XY0" => format!("skips the next instruction if {} is not {}", reg(x), reg(y)),
+        "6XNN" => format!("sets V{x:X} to {nn}"),
+        "7XNN" => format!(
+            "adds {nn} to {}, giving {} (no carry flag)",
+            reg(x),
+            vx.wrapping_add(nn)
+        ),
+        "8XY0" => format!("copies {} into V{x:X}", reg(y)),
+        "8XY1" => format!("sets V{x:X} to {} OR {} = {}", reg(x), reg(y), vx | vy),
+        "8XY2" => format!("sets V{x:X} to {} AND {} = {}", reg(x), reg(y), vx & vy),
+        "8XY3" => format!("sets V{x:X} to {} XOR {} = {}", reg(x), reg(y), vx ^ vy),
+        "8XY4" => {
+            let (sum, carry) = vx.overflowing_add(vy);
+            format!(
+                "adds {} to {}, giving {sum}; VF = {} ({})",
+                reg(y),
+                reg(x),
+                u8::from(carry),
+                if carry { "carry" } else { "no carry" }
+            )
+        }
+        "8XY5" => {
+            let (diff, borrow) = vx.overflowing_sub(vy);
+            format!(
+                "subtracts {} from {}, giving {diff}; VF = {} ({})",
+                reg(y),
+                reg(x),
+                u8::from(!borrow),
+                if borrow { "borrow" } else { "no borrow" }
+            )
+        }
+        "8XY7" => {
+            let (diff, borrow) = vy.overflowing_sub(vx);
+            format!(
+                "sets V{x:X} to {} minus {}, giving {diff}; VF = {} ({})",
+                reg(y),
+                reg(x),
+                u8::from(!borrow),
+                if borrow { "borrow" } else { "no borrow" }
+            )
+        }
+        "8XY6" => format!(
+            "shifts {} right by one into V{x:X}, the dropped bit goes to VF",
+            shift_source(ins, chip8)
+        ),
+        "8XYE" => format!(
+            "shifts {} left by one into V{x:X}, the dropped bit goes to VF",
+            shift_source(ins, chip8)
+        ),
+        "ANNN" => format!("points I at {nnn:#05X}"),
+        "BNNN" => {
+            let base = if chip8.quirks().jump_with_vx { x } else { 0 };
+            format!(
+                "jumps to {nnn:#05X} plus {}, landing on {:#05X}",
+                reg(base),
+                nnn + u16::from(regs[usize::from(base)])
+            )
+        }
+        "CXNN" => format!("sets V{x:X} to a random byte masked with {nn:#04X}"),
+        "DXY0" => {
+            format!("draws a 16x16 sprite from {i:#05X} at ({vx}, {vy}); VF is set on collision")
+        }
+        "DXYN" => {
+            format!("draws an 8x{n} sprite from {i:#05X} at ({vx}, {vy}); VF is set on collision")
+        }
+        "EX9E" => format!(
+            "skips the next instruction if key {:X} (from V{x:X}) is pressed, it {}",
+            vx & 0xF,
+            pressed(chip8, vx)
+        ),
+        "EXA1" => format!(
+            "skips the next instruction if key {:X} (from V{x:X}) is not pressed, it {}",
+            vx & 0xF,
+            pressed(chip8, vx)
+        ),
+        "FX07" => format!(
+            "copies the delay timer ({}) into V{x:X}",
+            chip8.delay_timer()
+        ),
+        "FX0A" => format!("waits for a key press and stores the key in V{x:X}"),
+        "FX15" => format!("sets the delay timer to {}", reg(x)),
+        "FX18" => format!("sets the sound timer to {}", reg(x)),
+        "FX1E" => format!(
+            "adds {} to I ({i:#05X}), giving {:#05X}",
+            reg(x),
+            i.wrapping_add(u16::from(vx))
+        ),
+        "FX29" => format!(
+            "points I at the font sprite for digit {:X} (from V{x:X})",
+            vx & 0xF
+        ),
+        "FX33" => format!(
+            "writes the decimal digits of {} ({}, {}, {}) to {i:#05X}..{:#05X}",
+            reg(x),
+            vx / 100,
+            (vx / 10) % 10,
+            vx % 10,
+            i.wrapping_add(2)
+        ),
+        "FX55" => format!("stores V0..V{x:X} into memory starting at {i:#05X}"),
+        "FX65" => format!("loads V0..V{x:X} from memory starting at {i:#05X}"),
+        // no operand values worth spelling out, fall back to the reference table
+        _ => info.description.to_string(),
+    }
+}
+
+/// what happened, for instructions whose result isn't obvious from `explain`.
+/// call after executing `ins`
+pub fn outcome(ins: &Instruction, after: &Chip8) -> Option<String> {
+    let vf = after.registers()[0xF];
+    let info = opcodes::lookup(ins.op, after.variant())?;
+    match info.pattern {
+        "DXY0" | "DXYN" => Some(if vf != 0 {
+            "collision flag set".to_string()
+        } else {
+            "no collision".to_string()
+        }),
+        "3XNN" | "4XNN" | "5XY0" | "9XY0" | "EX9E" | "EXA1" => {
+            Some(if after.program_counter() == ins.addr + 4 {
+                "skipped".to_string()
+            } else {
+                "not skipped".to_string()
+            })
+        }
+        "8XY6" | "8XYE" => Some(format!("VF = {vf}")),
+        "FX0A" if after.program_counter() == ins.addr => Some("still waiting".to_string()),
+        _ => None,
+    }
+}
+
+fn shift_source(ins: &Instruction, chip8: &Chip8) -> String {
+    let idx = if chip8.quirks().shift_uses_vy {
+        ins.y
+    } else {
+        ins.x
+    };
+    format!("V{idx:X} ({})", chip8.registers()[usize::from(idx)])
+}
+
+fn pressed(chip8: &Chip8, key: u8) -> &'static str {
+    if chip8.keys()[usize::from(key & 0xF)] {
+        "is"
+    } else {
+        "isn't"
+    }
+}
@@ -0,0 +1,52 @@
+//! running a machine without a window, handing every frame to a `FrameSink`
+
+use crate::emulator::core::Chip8;
+use crate::emulator::error::Chip8Error;
+use crate::emulator::state::{ProgramState, Screen, TimerState};
+
+/// what happened during a frame besides the picture
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameStats {
+    /// counted from 0 at the start of the run
+    pub frame: u64,
+    pub state: ProgramState,
+    pub timers: TimerState,
+}
+
+/// receives the screen after every frame of `run`, e.g. to hash frames, encode
+/// video or stream them somewhere
+pub trait FrameSink {
+    fn on_frame(&mut self, screen: &Screen, stats: &FrameStats);
+}
+
+impl<F: FnMut(&Screen, &FrameStats)> FrameSink for F {
+    fn on_frame(&mut self, screen: &Screen, stats: &FrameStats) {
+        self(screen, stats)
+    }
+}
+
+/// step `chip8` up to `frames` frames of `cycles` instructions, passing each one to `sink`.
+/// stops early after a frame that didn't end `Running`, that frame is still passed on.
+/// returns the state of the last frame
+pub fn run(
+    chip8: &mut Chip8,
+    frames: u64,
+    cycles: u32,
+    sink: &mut impl FrameSink,
+) -> Result<ProgramState, Chip8Error> {
+    let mut state = ProgramState::Running;
+    for frame in 0..frames {
+        let (frame_state, timers) = chip8.step_frame(cycles)?;
+        state = frame_state;
+        let stats = FrameStats {
+            frame,
+            state,
+            timers,
+        };
+        sink.on_frame(chip8.get_screen(), &stats);
+        if state != ProgramState::Running {
+            break;
+        }
+    }
+    Ok(state)
+}
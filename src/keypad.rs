@@ -0,0 +1,114 @@
+//! 4x4 hex keypad widget for debug views and overlays
+
+use crate::emulator::core::NUM_KEYS;
+use crate::text::{self, GLYPH_HEIGHT, GLYPH_WIDTH};
+
+/// keys in the order they sit on the original cosmac vip keypad
+pub const LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// a glyph with one pixel of padding on each side
+const CELL_WIDTH: usize = GLYPH_WIDTH + 2;
+const CELL_HEIGHT: usize = GLYPH_HEIGHT + 2;
+
+const GRID_WIDTH: usize = CELL_WIDTH * 4;
+const GRID_HEIGHT: usize = CELL_HEIGHT * 4;
+
+/// the "waiting" label is drawn right of the grid
+const LABEL_X: usize = GRID_WIDTH + 2;
+
+/// total size of the widget in pixels
+pub const KEYPAD_WIDTH: usize = LABEL_X + text::GLYPH_ADVANCE * 3 - 1;
+pub const KEYPAD_HEIGHT: usize = GRID_HEIGHT;
+
+/// draw the keypad with its top left corner at (x, y).
+///
+/// released keys are drawn as a lit glyph, pressed keys as an inverted cell.
+/// `waiting` is the register an `FX0A` is blocked on, see `Chip8::waiting_for_key`.
+/// `plot(x, y, lit)` is called for every pixel of the widget's grid
+pub fn draw_keypad_with(
+    keys: &[bool; NUM_KEYS],
+    waiting: Option<u8>,
+    x: usize,
+    y: usize,
+    mut plot: impl FnMut(usize, usize, bool),
+) {
+    for (row, keys_in_row) in LAYOUT.iter().enumerate() {
+        for (col, key) in keys_in_row.iter().enumerate() {
+            let left = x + col * CELL_WIDTH;
+            let top = y + row * CELL_HEIGHT;
+            let pressed = keys[usize::from(*key)];
+
+            for py in top..top + CELL_HEIGHT {
+                for px in left..left + CELL_WIDTH {
+                    plot(px, py, pressed);
+                }
+            }
+            let label = format!("{key:X}");
+            text::draw_text_with(&label, left + 1, top + 1, |px, py| plot(px, py, !pressed));
+        }
+    }
+
+    if let Some(reg) = waiting {
+        let label = format!("KEY\nV{reg:X}?");
+        text::draw_text_with(&label, x + LABEL_X, y + 1, |px, py| plot(px, py, true));
+    }
+}
+
+/// draw the keypad into a row-major boolean buffer `width` pixels wide, clipping at the edges
+pub fn draw_keypad(
+    buf: &mut [bool],
+    width: usize,
+    x: usize,
+    y: usize,
+    keys: &[bool; NUM_KEYS],
+    waiting: Option<u8>,
+) {
+    let height = buf.len() / width.max(1);
+    draw_keypad_with(keys, waiting, x, y, |px, py, lit| {
+        if px < width && py < height {
+            buf[py * width + px] = lit;
+        }
+    });
+}
+
+/// the key under pixel (x, y) of a keypad drawn with its top left corner at
+/// (left, top), e.g. a click mapped through `Viewport::to_pixel`
+pub fn key_at(x: usize, y: usize, left: usize, top: usize) -> Option<u8> {
+    let col = x.checked_sub(left)? / CELL_WIDTH;
+    let row = y.checked_sub(top)? / CELL_HEIGHT;
+    LAYOUT.get(row)?.get(col).copied()
+}
+
+/// where `draw_input_display` puts the keypad
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+/// gap between the input display and the edges of the buffer
+const INPUT_DISPLAY_MARGIN: usize = 1;
+
+/// the keypad grid in a corner of a row-major boolean buffer `width` pixels wide,
+/// with the pressed keys highlighted. meant for streams and tutorial recordings,
+/// so unlike `draw_keypad` there's no `FX0A` label
+pub fn draw_input_display(buf: &mut [bool], width: usize, keys: &[bool; NUM_KEYS], corner: Corner) {
+    let height = buf.len() / width.max(1);
+    let right = width.saturating_sub(GRID_WIDTH + INPUT_DISPLAY_MARGIN);
+    let bottom = height.saturating_sub(GRID_HEIGHT + INPUT_DISPLAY_MARGIN);
+    let (x, y) = match corner {
+        Corner::TopLeft => (INPUT_DISPLAY_MARGIN, INPUT_DISPLAY_MARGIN),
+        Corner::TopRight => (right, INPUT_DISPLAY_MARGIN),
+        Corner::BottomLeft => (INPUT_DISPLAY_MARGIN, bottom),
+        Corner::BottomRight => (right, bottom),
+    };
+    draw_keypad(buf, width, x, y, keys, None);
+}
@@ -0,0 +1,40 @@
+pub mod analysis;
+pub mod emulator;
+pub mod headless;
+
+#[cfg(feature = "tools")]
+pub mod asm;
+#[cfg(feature = "tools")]
+pub mod callgraph;
+#[cfg(feature = "tools")]
+pub mod explain;
+#[cfg(feature = "tools")]
+pub mod hash;
+#[cfg(feature = "tools")]
+pub mod solver;
+#[cfg(feature = "tools")]
+pub mod sprite;
+#[cfg(feature = "tools")]
+pub mod stress;
+#[cfg(feature = "tools")]
+pub mod timeline;
+
+#[cfg(feature = "overlay")]
+pub mod collisionview;
+#[cfg(feature = "overlay")]
+pub mod keypad;
+#[cfg(feature = "overlay")]
+pub mod osd;
+#[cfg(feature = "overlay")]
+pub mod text;
+#[cfg(feature = "overlay")]
+pub mod timergraph;
+#[cfg(feature = "overlay")]
+pub mod viewport;
+
+#[cfg(feature = "timing")]
+pub mod pacer;
+#[cfg(feature = "timing")]
+pub mod profiler;
+#[cfg(feature = "timing")]
+pub mod runahead;
@@ -1,7 +1,674 @@
-mod emulator;
+use chip8_emu::emulator::core::{Chip8, START_ADDR};
+use chip8_emu::emulator::instruction::Instruction;
+use chip8_emu::emulator::opcodes;
+use chip8_emu::emulator::quirks::Quirks;
+use chip8_emu::emulator::state::{ProgramState, Screen};
+use chip8_emu::emulator::variant::Variant;
+use chip8_emu::headless::{self, FrameStats};
+use chip8_emu::profiler::{self, BudgetTracker};
+use chip8_emu::{analysis, asm, explain, hash, sprite, stress};
+use std::cell::RefCell;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+use std::{env, fs, process};
+
+const USAGE: &str = "usage: chip8-emu info <rom>
+       chip8-emu teach <rom>
+       chip8-emu compare-run <rom-a> [rom-b] [--frames N] [--cycles N]
+                             [--quirk-a NAME] [--quirk-b NAME] [--image FILE.pbm]
+       chip8-emu detect-quirks <rom> [--frames N] [--cycles N]
+       chip8-emu budgets <rom> <project-file> [--frames N] [--cycles N]
+       chip8-emu gen-stress <draw|calls|memory|resolution> <out>
+       chip8-emu extract <rom> --sprites <dir>
+       chip8-emu sprite <image.png|image.pbm|image.pgm> [--binary <out>]
+       chip8-emu assemble <source> <out> [--variant NAME]";
+
+/// instructions per frame when a command doesn't get `--cycles`
+const DEFAULT_CYCLES: u32 = 10;
 
 fn main() {
-    let mut chip8 = emulator::core::Chip8::new();
-    chip8.tick();
-    chip8.tick_timers();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["info", rom] => info(rom),
+        ["teach", rom] => teach(rom),
+        ["compare-run", rest @ ..] => compare_run(rest),
+        ["detect-quirks", rest @ ..] => detect_quirks(rest),
+        ["budgets", rest @ ..] => budgets(rest),
+        ["gen-stress", kind, out] => gen_stress(kind, out),
+        ["extract", rom, "--sprites", dir] => extract_sprites(rom, dir),
+        ["sprite", image] => sprite(image, None),
+        ["sprite", image, "--binary", out] => sprite(image, Some(out)),
+        ["assemble", source, out] => assemble(source, out, Variant::Chip8),
+        ["assemble", source, out, "--variant", name] => assemble(source, out, parse_variant(name)),
+        _ => usage(),
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{USAGE}");
+    process::exit(2);
+}
+
+fn parse_number(flag: &str, value: Option<&&str>) -> u32 {
+    match value.map(|value| value.parse()) {
+        Some(Ok(number)) => number,
+        _ => {
+            eprintln!("{flag} expects a number");
+            process::exit(2);
+        }
+    }
+}
+
+/// flip a quirk from its default, by `Quirks` field name
+fn toggle_quirk(quirks: &mut Quirks, name: &str) {
+    match quirks.get(name) {
+        Some(enabled) => {
+            quirks.set(name, !enabled);
+        }
+        None => {
+            eprintln!(
+                "unknown quirk {name}, expected one of: {}",
+                Quirks::NAMES.join(", ")
+            );
+            process::exit(2);
+        }
+    }
+}
+
+fn read_rom(path: &str) -> Vec<u8> {
+    fs::read(path).unwrap_or_else(|err| {
+        eprintln!("could not read {path}: {err}");
+        process::exit(1);
+    })
+}
+
+/// print size, checksums, a variant guess and an opcode histogram of a rom
+fn info(path: &str) {
+    let rom = read_rom(path);
+
+    println!("file:  {path}");
+    println!("size:  {} bytes", rom.len());
+    println!("crc32: {:08x}", hash::crc32(&rom));
+    println!("sha1:  {}", hash::to_hex(&hash::sha1(&rom)));
+
+    let report = analysis::detect_variant(&rom);
+    println!(
+        "variant: {} (schip opcodes: {}, xo-chip opcodes: {}, chip-8x opcodes: {})",
+        report.recommended.name(),
+        report.schip_hits,
+        report.xo_chip_hits,
+        report.chip_8x_hits
+    );
+    let quirks: Vec<&str> = Quirks::NAMES
+        .iter()
+        .copied()
+        .filter(|name| report.quirks.get(name) == Some(true))
+        .collect();
+    println!("quirks:  {}", quirks.join(", "));
+    if let Some(variant) = report.unsupported {
+        println!(
+            "warning: looks written for {}, which isn't supported",
+            variant.name()
+        );
+    }
+
+    let mut histogram: Vec<_> = analysis::opcode_histogram(&rom).into_iter().collect();
+    histogram.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    println!("opcodes:");
+    for (family, count) in histogram {
+        println!("  {family}  {count}");
+    }
+
+    let reach = analysis::reachability(&rom, report.recommended);
+    let keys: Vec<String> = reach.keys.iter().map(|key| format!("{key:X}")).collect();
+    let mut notes = Vec::new();
+    if reach.waits_for_key {
+        notes.push("waits for any key".to_string());
+    }
+    if reach.unknown_key_tests > 0 {
+        notes.push(match reach.unknown_key_tests {
+            1 => "1 test of an unknown key".to_string(),
+            count => format!("{count} tests of unknown keys"),
+        });
+    }
+    match (keys.is_empty(), notes.is_empty()) {
+        (true, true) => println!("keys:  none found"),
+        (_, true) => println!("keys:  {}", keys.join(" ")),
+        (true, false) => println!("keys:  none found ({})", notes.join(", ")),
+        (false, false) => println!("keys:  {} ({})", keys.join(" "), notes.join(", ")),
+    }
+}
+
+/// step through a rom one instruction at a time, explaining each one.
+/// enter steps, a number steps that many instructions, q quits
+fn teach(path: &str) {
+    let mut chip8 = machine_for(&read_rom(path), Quirks::default());
+
+    println!(
+        "{} loaded, enter to step, a number to step that many, q to quit",
+        path
+    );
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("> ");
+        let _ = io::stdout().flush();
+        let Some(Ok(line)) = lines.next() else {
+            return;
+        };
+        let steps = match line.trim() {
+            "q" => return,
+            "" => 1,
+            count => match count.parse::<u32>() {
+                Ok(count) => count,
+                Err(_) => {
+                    println!("expected a number or q");
+                    continue;
+                }
+            },
+        };
+
+        for _ in 0..steps {
+            let addr = chip8.program_counter();
+            let ins = Instruction::decode(addr, chip8.memory().read_u16(addr));
+            let explanation = explain::explain(&ins, &chip8);
+            let state = match chip8.tick() {
+                Ok(state) => state,
+                Err(err) => {
+                    println!("{addr:#05X}  {:04X}  {err}", ins.op);
+                    return;
+                }
+            };
+
+            let mnemonic =
+                opcodes::lookup(ins.op, chip8.variant()).map_or("???", |info| info.mnemonic);
+            match explain::outcome(&ins, &chip8) {
+                Some(outcome) => {
+                    println!(
+                        "{addr:#05X}  {:04X}  {mnemonic:<15}{explanation}; {outcome}",
+                        ins.op
+                    )
+                }
+                None => println!("{addr:#05X}  {:04X}  {mnemonic:<15}{explanation}", ins.op),
+            }
+            if let ProgramState::Halted { .. } = state {
+                println!("the program has halted");
+                return;
+            }
+        }
+    }
+}
+
+/// a machine set up for `rom`, with the variant `detect_variant` suggests
+fn machine_for(rom: &[u8], quirks: Quirks) -> Chip8 {
+    let variant = analysis::detect_variant(rom).recommended;
+    let mut chip8 = Chip8::new_with_variant_and_quirks(variant, quirks);
+    // the same random bytes on every run, so compared machines only differ in what was asked for
+    chip8.set_rng_seed(0);
+    if let Err(err) = chip8.load_rom(rom) {
+        eprintln!("{err}");
+        process::exit(1);
+    }
+    chip8
+}
+
+/// run two roms (or one rom under two quirk sets) side by side without input
+/// and report the first frame their screens differ. exits with 1 when they do,
+/// and 3 when a machine stopped before all the frames ran
+fn compare_run(args: &[&str]) {
+    let mut roms = Vec::new();
+    let mut frames = 600;
+    let mut cycles = DEFAULT_CYCLES;
+    let (mut quirks_a, mut quirks_b) = (Quirks::default(), Quirks::default());
+    let mut image = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match *arg {
+            "--frames" => frames = parse_number(arg, args.next()),
+            "--cycles" => cycles = parse_number(arg, args.next()),
+            "--quirk-a" => toggle_quirk(&mut quirks_a, args.next().unwrap_or_else(|| usage())),
+            "--quirk-b" => toggle_quirk(&mut quirks_b, args.next().unwrap_or_else(|| usage())),
+            "--image" => image = Some(*args.next().unwrap_or_else(|| usage())),
+            flag if flag.starts_with("--") => usage(),
+            rom => roms.push(rom),
+        }
+    }
+    let (path_a, path_b) = match roms.as_slice() {
+        [rom] => (*rom, *rom),
+        [rom_a, rom_b] => (*rom_a, *rom_b),
+        _ => usage(),
+    };
+
+    let mut a = machine_for(&read_rom(path_a), quirks_a);
+    let mut b = machine_for(&read_rom(path_b), quirks_b);
+    // frames whose screens were compared, fewer than asked for if a machine stops early
+    let mut compared = 0;
+    for frame in 0..frames {
+        let (state_a, state_b) = match (a.step_frame(cycles), b.step_frame(cycles)) {
+            (Ok((state_a, _)), Ok((state_b, _))) => (state_a, state_b),
+            (Err(err), _) => {
+                println!("a stopped at frame {frame}: {err}");
+                break;
+            }
+            (_, Err(err)) => {
+                println!("b stopped at frame {frame}: {err}");
+                break;
+            }
+        };
+
+        if a.get_screen() != b.get_screen() {
+            println!("screens diverge at frame {frame}");
+            if let Some(path) = image {
+                write_side_by_side(path, a.get_screen(), b.get_screen());
+                println!("wrote {path}");
+            }
+            process::exit(1);
+        }
+        compared = frame + 1;
+        if state_a != ProgramState::Running || state_b != ProgramState::Running {
+            println!("stopped at frame {frame}: a {state_a:?}, b {state_b:?}");
+            break;
+        }
+    }
+    if compared < frames {
+        println!("screens identical for the {compared} of {frames} frames that ran");
+        process::exit(3);
+    }
+    println!("screens identical for {frames} frames");
+}
+
+/// both screens as a plain pbm, a on the left and b on the right with a one pixel gap
+fn write_side_by_side(path: &str, a: &Screen, b: &Screen) {
+    let height = a.height().max(b.height());
+    let width = a.width() + 1 + b.width();
+    let pixel = |screen: &Screen, x: usize, y: usize| {
+        x < screen.width() && y < screen.height() && screen.get_pixel(x, y)
+    };
+
+    let mut out = format!("P1\n{width} {height}\n");
+    for y in 0..height {
+        let row: Vec<&str> = (0..width)
+            .map(|x| {
+                let lit = if x < a.width() {
+                    pixel(a, x, y)
+                } else if x > a.width() {
+                    pixel(b, x - a.width() - 1, y)
+                } else {
+                    true
+                };
+                if lit { "1" } else { "0" }
+            })
+            .collect();
+        out.push_str(&row.join(" "));
+        out.push('\n');
+    }
+    if let Err(err) = fs::write(path, out) {
+        eprintln!("could not write {path}: {err}");
+        process::exit(1);
+    }
+}
+
+/// trying every combination gets slow quickly, past this many quirks only
+/// the first ones are varied
+const MAX_DETECTED_QUIRKS: usize = 8;
+
+/// how a rom fared under one set of quirks
+enum ProbeResult {
+    /// hit an unimplemented opcode, a stack over/underflow or similar
+    Fault,
+    /// ran off the end of memory
+    Crashed,
+    /// still running or exited on its own, with this many distinct screens
+    /// over the second half of the run
+    Ran {
+        lit_pixels: usize,
+        distinct_late_screens: usize,
+    },
+}
+
+/// run the rom under each combination of the quirks its opcodes depend on and
+/// suggest the combination that behaves best
+fn detect_quirks(args: &[&str]) {
+    let mut path = None;
+    let mut frames = 300;
+    let mut cycles = DEFAULT_CYCLES;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match *arg {
+            "--frames" => frames = parse_number(arg, args.next()),
+            "--cycles" => cycles = parse_number(arg, args.next()),
+            flag if flag.starts_with("--") => usage(),
+            rom if path.is_none() => path = Some(rom),
+            _ => usage(),
+        }
+    }
+    let Some(path) = path else { usage() };
+    let rom = read_rom(path);
+
+    let variant = analysis::detect_variant(&rom).recommended;
+    let mut relevant: Vec<_> = analysis::quirks_used(&rom, variant).into_iter().collect();
+    if relevant.len() > MAX_DETECTED_QUIRKS {
+        println!(
+            "only varying {} of {} relevant quirks: {}",
+            MAX_DETECTED_QUIRKS,
+            relevant.len(),
+            relevant[MAX_DETECTED_QUIRKS..].join(", ")
+        );
+        relevant.truncate(MAX_DETECTED_QUIRKS);
+    }
+    println!("variant: {}", variant.name());
+    println!("quirks the rom can depend on: {}", relevant.join(", "));
+
+    let mut best: Option<(usize, Quirks, usize)> = None;
+    for mask in 0..1u32 << relevant.len() {
+        let mut quirks = Quirks::default();
+        for (bit, name) in relevant.iter().enumerate() {
+            if mask & (1 << bit) != 0 {
+                toggle_quirk(&mut quirks, name);
+            }
+        }
+
+        let toggled: Vec<_> = relevant
+            .iter()
+            .enumerate()
+            .filter(|(bit, _)| mask & (1 << bit) != 0)
+            .map(|(_, name)| *name)
+            .collect();
+        let label = if toggled.is_empty() {
+            "defaults".to_string()
+        } else {
+            toggled.join(" ")
+        };
+
+        let result = probe(&rom, quirks, frames, cycles);
+        let score = match result {
+            ProbeResult::Fault => {
+                println!("  fault    {label}");
+                continue;
+            }
+            ProbeResult::Crashed => {
+                println!("  crashed  {label}");
+                continue;
+            }
+            ProbeResult::Ran {
+                lit_pixels,
+                distinct_late_screens,
+            } => {
+                println!(
+                    "  ran      {label} ({lit_pixels} pixels lit, {distinct_late_screens} distinct screens late in the run)"
+                );
+                // something on screen matters most, then a stable picture
+                usize::from(lit_pixels > 0) * 1000 + 1000 / distinct_late_screens.max(1)
+            }
+        };
+
+        // ties go to the combination closest to the defaults
+        let better = match best {
+            None => true,
+            Some((best_score, _, best_toggled)) => {
+                score > best_score || (score == best_score && toggled.len() < best_toggled)
+            }
+        };
+        if better {
+            best = Some((score, quirks, toggled.len()));
+        }
+    }
+
+    match best {
+        Some((_, quirks, _)) => {
+            let changed: Vec<_> = Quirks::NAMES
+                .iter()
+                .filter(|name| quirks.get(name) != Quirks::default().get(name))
+                .map(|name| format!("{name}={}", quirks.get(name).unwrap_or_default()))
+                .collect();
+            if changed.is_empty() {
+                println!("suggested: the default quirks");
+            } else {
+                println!("suggested: {}", changed.join(" "));
+            }
+        }
+        None => println!("the rom faults or crashes under every combination"),
+    }
+}
+
+fn probe(rom: &[u8], quirks: Quirks, frames: u32, cycles: u32) -> ProbeResult {
+    let mut chip8 = machine_for(rom, quirks);
+    let mut late_screens: Vec<Screen> = Vec::new();
+    let mut collect_late = |screen: &Screen, stats: &FrameStats| {
+        if stats.frame >= u64::from(frames / 2) && !late_screens.contains(screen) {
+            late_screens.push(screen.clone());
+        }
+    };
+    match headless::run(&mut chip8, u64::from(frames), cycles, &mut collect_late) {
+        Err(_) => return ProbeResult::Fault,
+        Ok(ProgramState::Halted {
+            requested_by_rom: false,
+        }) => return ProbeResult::Crashed,
+        Ok(_) => {}
+    }
+
+    let screen = chip8.get_screen();
+    let lit_pixels = (0..screen.height())
+        .flat_map(|y| (0..screen.width()).map(move |x| (x, y)))
+        .filter(|(x, y)| screen.get_pixel(*x, *y))
+        .count();
+    ProbeResult::Ran {
+        lit_pixels,
+        distinct_late_screens: late_screens.len(),
+    }
+}
+
+/// run a rom without input and report every frame in which a region of the project
+/// file went over its instruction budget, exits with 1 if any did
+fn budgets(args: &[&str]) {
+    let mut paths = Vec::new();
+    let mut frames = 600;
+    let mut cycles = DEFAULT_CYCLES;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match *arg {
+            "--frames" => frames = parse_number(arg, args.next()),
+            "--cycles" => cycles = parse_number(arg, args.next()),
+            flag if flag.starts_with("--") => usage(),
+            path => paths.push(path),
+        }
+    }
+    let [rom_path, project_path] = paths.as_slice() else {
+        usage()
+    };
+    let rom = read_rom(rom_path);
+    let project = fs::read_to_string(project_path).unwrap_or_else(|err| {
+        eprintln!("could not read {project_path}: {err}");
+        process::exit(1);
+    });
+    let budgets = profiler::parse_budgets(&project).unwrap_or_else(|err| {
+        eprintln!("{project_path}: {err}");
+        process::exit(1);
+    });
+
+    let quirks = analysis::detect_variant(&rom).quirks;
+    let mut chip8 = machine_for(&rom, quirks);
+    let tracker = Rc::new(RefCell::new(BudgetTracker::new(budgets)));
+    let counter = Rc::clone(&tracker);
+    chip8.add_hook_all(move |instruction| counter.borrow_mut().record(instruction.addr));
+
+    let (mut ran, mut over) = (0, 0);
+    let mut report = |_: &Screen, stats: &FrameStats| {
+        ran += 1;
+        let alerts = tracker.borrow_mut().end_frame();
+        if !alerts.is_empty() {
+            over += 1;
+        }
+        for alert in alerts {
+            println!(
+                "frame {}: {} ran {} instructions, its budget is {}",
+                stats.frame, alert.region, alert.executed, alert.max_per_frame
+            );
+        }
+    };
+    if let Err(err) = headless::run(&mut chip8, u64::from(frames), cycles, &mut report) {
+        eprintln!("{err}");
+        process::exit(1);
+    }
+
+    println!("{over} of {ran} frames went over budget");
+    if over > 0 {
+        process::exit(1);
+    }
+}
+
+/// write one of the synthetic stress roms to `out`
+fn gen_stress(kind: &str, out: &str) {
+    let Some(rom) = stress::generate(kind) else {
+        eprintln!(
+            "unknown stress rom {kind}, expected one of: {}",
+            stress::KINDS.join(", ")
+        );
+        process::exit(2);
+    };
+    if let Err(err) = fs::write(out, &rom) {
+        eprintln!("could not write {out}: {err}");
+        process::exit(1);
+    }
+    println!("wrote {} bytes to {out}", rom.len());
+}
+
+/// write every sprite a reachable draw instruction uses as a png, plus an
+/// include of all of them as labelled `db` lines that `assemble` accepts
+fn extract_sprites(path: &str, dir: &str) {
+    let rom = read_rom(path);
+    let variant = analysis::detect_variant(&rom).recommended;
+    let reachability = analysis::reachability(&rom, variant);
+
+    if let Err(err) = fs::create_dir_all(dir) {
+        eprintln!("could not create {dir}: {err}");
+        process::exit(1);
+    }
+
+    let mut include = String::new();
+    let mut count = 0;
+    for sprite in &reachability.sprites {
+        // the font and anything the rom builds at runtime aren't part of the image
+        let Some(bytes) = sprite
+            .addr
+            .checked_sub(START_ADDR)
+            .and_then(|offset| rom.get(offset..offset + sprite.len()))
+        else {
+            continue;
+        };
+        if sprite.is_empty() {
+            continue;
+        }
+
+        let name = format!(
+            "sprite_{:03x}_{}x{}",
+            sprite.addr, sprite.width, sprite.height
+        );
+        let file = format!("{dir}/{name}.png");
+        let written = sprite::write_png(&sprite::decode(bytes, sprite.width))
+            .and_then(|png| fs::write(&file, png).map_err(|err| err.to_string()));
+        if let Err(err) = written {
+            eprintln!("could not write {file}: {err}");
+            process::exit(1);
+        }
+
+        include.push_str(&format!("{name}:\n"));
+        for row in bytes.chunks(sprite.width / 8) {
+            let data: Vec<String> = row.iter().map(|byte| format!("0x{byte:02X}")).collect();
+            include.push_str(&format!("  db {}\n", data.join(", ")));
+        }
+        count += 1;
+    }
+
+    let file = format!("{dir}/sprites.asm");
+    if let Err(err) = fs::write(&file, include) {
+        eprintln!("could not write {file}: {err}");
+        process::exit(1);
+    }
+    println!(
+        "{count} sprites from {} reachable instructions written to {dir}",
+        reachability.code.len()
+    );
+}
+
+/// convert an image into sprite bytes, printed as `db` lines for `assemble` or written raw to `out`
+fn sprite(path: &str, out: Option<&str>) {
+    let file = read_rom(path);
+    let image = if file.starts_with(sprite::PNG_SIGNATURE) {
+        sprite::parse_png(&file)
+    } else {
+        String::from_utf8(file)
+            .map_err(|_| "not a png or a plain pbm/pgm".to_string())
+            .and_then(|text| sprite::parse_netpbm(&text))
+    };
+    let encoded = image.and_then(|image| Ok((sprite::encode(&image)?, image.width)));
+    let (bytes, width) = encoded.unwrap_or_else(|err| {
+        eprintln!("{path}: {err}");
+        process::exit(1);
+    });
+
+    match out {
+        Some(out) => {
+            if let Err(err) = fs::write(out, &bytes) {
+                eprintln!("could not write {out}: {err}");
+                process::exit(1);
+            }
+            println!("wrote {} bytes to {out}", bytes.len());
+        }
+        None => {
+            // one row of the sprite per line
+            for row in bytes.chunks(width.div_ceil(8)) {
+                let data: Vec<String> = row.iter().map(|byte| format!("0x{byte:02X}")).collect();
+                println!("db {}", data.join(", "));
+            }
+        }
+    }
+}
+
+/// a variant by its name as `Variant::name` spells it, ignoring case
+fn parse_variant(name: &str) -> Variant {
+    Variant::ALL
+        .into_iter()
+        .find(|variant| variant.name().eq_ignore_ascii_case(name))
+        .unwrap_or_else(|| {
+            let names: Vec<_> = Variant::ALL.iter().map(|variant| variant.name()).collect();
+            eprintln!(
+                "unknown variant {name}, expected one of: {}",
+                names.join(", ")
+            );
+            process::exit(1);
+        })
+}
+
+/// assemble a source file into a rom, errors point at the offending text
+fn assemble(path: &str, out: &str, variant: Variant) {
+    let source = fs::read_to_string(path).unwrap_or_else(|err| {
+        eprintln!("could not read {path}: {err}");
+        process::exit(1);
+    });
+    let rom = asm::assemble(&source, variant).unwrap_or_else(|errors| {
+        for err in &errors {
+            eprintln!("{path}:{err}");
+            let line = source.lines().nth(err.line - 1).unwrap_or("");
+            eprintln!("    {line}");
+            eprintln!("    {}{}", " ".repeat(err.column - 1), "^".repeat(err.len));
+        }
+        match errors.len() {
+            1 => eprintln!("1 error"),
+            count => eprintln!("{count} errors"),
+        }
+        process::exit(1);
+    });
+
+    if let Err(err) = fs::write(out, &rom) {
+        eprintln!("could not write {out}: {err}");
+        process::exit(1);
+    }
+    println!("wrote {} bytes to {out}", rom.len());
 }
@@ -0,0 +1,92 @@
+use crate::text;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+pub const DEFAULT_MESSAGE_DURATION: Duration = Duration::from_secs(2);
+
+/// older messages get dropped once this many are on screen
+const MAX_MESSAGES: usize = 4;
+
+pub struct OsdMessage {
+    text: String,
+    remaining: Duration,
+}
+
+impl OsdMessage {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.remaining
+    }
+}
+
+/// queue of short lived on-screen messages ("Paused", "State saved to slot 2", ...).
+///
+/// the engine side only keeps track of what to show and for how long,
+/// frontends call `advance` once per frame and draw `messages`.
+#[derive(Default)]
+pub struct Osd {
+    messages: VecDeque<OsdMessage>,
+}
+
+impl Osd {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// show a message for the default duration
+    pub fn push(&mut self, text: impl Into<String>) {
+        self.push_for(text, DEFAULT_MESSAGE_DURATION);
+    }
+
+    pub fn push_for(&mut self, text: impl Into<String>, duration: Duration) {
+        let text = text.into();
+        // the same message twice in a row (e.g. a held hotkey) just restarts its timer
+        self.messages.retain(|msg| msg.text != text);
+        if self.messages.len() == MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(OsdMessage {
+            text,
+            remaining: duration,
+        });
+    }
+
+    /// age all messages by `elapsed`, dropping the expired ones
+    pub fn advance(&mut self, elapsed: Duration) {
+        for msg in self.messages.iter_mut() {
+            msg.remaining = msg.remaining.saturating_sub(elapsed);
+        }
+        self.messages.retain(|msg| !msg.remaining.is_zero());
+    }
+
+    /// messages currently on screen, oldest first
+    pub fn messages(&self) -> impl Iterator<Item = &OsdMessage> {
+        self.messages.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.messages.clear();
+    }
+
+    /// draw the messages into the bottom left corner of a row-major boolean buffer,
+    /// newest message at the bottom
+    pub fn render(&self, buf: &mut [bool], width: usize) {
+        let height = buf.len() / width.max(1);
+        let mut bottom = height.saturating_sub(1);
+        for msg in self.messages.iter().rev() {
+            let top = (bottom + 1).saturating_sub(text::text_height(&msg.text));
+            text::draw_text(buf, width, 1, top, &msg.text);
+            if top < text::LINE_HEIGHT {
+                break;
+            }
+            bottom = top - 2;
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_FPS: u32 = 60;
+
+/// how long before the deadline we stop sleeping and spin instead,
+/// OS sleeps tend to overshoot by a fraction of a millisecond
+const SPIN_MARGIN: Duration = Duration::from_micros(250);
+
+/// keeps a frontend's run loop on a fixed frame cadence without busy-looping.
+///
+/// call `wait` once per frame, after emulating and rendering it.
+pub struct FramePacer {
+    frame_duration: Duration,
+    next_frame: Instant,
+}
+
+impl Default for FramePacer {
+    fn default() -> Self {
+        Self::new(DEFAULT_FPS)
+    }
+}
+
+impl FramePacer {
+    pub fn new(fps: u32) -> Self {
+        let frame_duration = Duration::from_secs(1) / fps.max(1);
+        Self {
+            frame_duration,
+            next_frame: Instant::now() + frame_duration,
+        }
+    }
+
+    pub fn frame_duration(&self) -> Duration {
+        self.frame_duration
+    }
+
+    /// time left before the next frame is due, zero if it is already late
+    pub fn until_next_frame(&self) -> Duration {
+        self.next_frame.saturating_duration_since(Instant::now())
+    }
+
+    /// restart the cadence from now, e.g. after unpausing
+    pub fn reset(&mut self) {
+        self.next_frame = Instant::now() + self.frame_duration;
+    }
+
+    /// block until the next frame is due, returns how long was spent waiting
+    pub fn wait(&mut self) -> Duration {
+        let started = Instant::now();
+
+        if let Some(sleep_for) = self
+            .next_frame
+            .saturating_duration_since(started)
+            .checked_sub(SPIN_MARGIN)
+        {
+            thread::sleep(sleep_for);
+        }
+        while Instant::now() < self.next_frame {
+            thread::yield_now();
+        }
+
+        let now = Instant::now();
+        self.next_frame += self.frame_duration;
+        // if we fell more than a frame behind don't try to catch up with a burst
+        // of frames, just resync to the current time
+        if self.next_frame < now {
+            self.next_frame = now + self.frame_duration;
+        }
+
+        now - started
+    }
+}
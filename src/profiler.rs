@@ -0,0 +1,404 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// number of frames kept around for the overlay graph (~2 seconds at 60Hz)
+pub const HISTORY_LEN: usize = 120;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Emulation,
+    Rendering,
+    Waiting,
+}
+
+/// time spent in each phase during a single frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameTimings {
+    pub emulation: Duration,
+    pub rendering: Duration,
+    pub waiting: Duration,
+}
+
+impl FrameTimings {
+    pub fn total(&self) -> Duration {
+        self.emulation + self.rendering + self.waiting
+    }
+
+    pub fn get(&self, phase: Phase) -> Duration {
+        match phase {
+            Phase::Emulation => self.emulation,
+            Phase::Rendering => self.rendering,
+            Phase::Waiting => self.waiting,
+        }
+    }
+
+    fn add(&mut self, phase: Phase, elapsed: Duration) {
+        match phase {
+            Phase::Emulation => self.emulation += elapsed,
+            Phase::Rendering => self.rendering += elapsed,
+            Phase::Waiting => self.waiting += elapsed,
+        }
+    }
+}
+
+/// measures where the time goes in a frontend's frame loop.
+///
+/// frontends call `begin` when switching phase and `end_frame` once per frame,
+/// the committed frames can then be read back for stats or drawn as a graph.
+pub struct FrameProfiler {
+    current: FrameTimings,
+    running: Option<(Phase, Instant)>,
+    history: VecDeque<FrameTimings>,
+}
+
+impl Default for FrameProfiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameProfiler {
+    pub fn new() -> Self {
+        Self {
+            current: FrameTimings::default(),
+            running: None,
+            history: VecDeque::with_capacity(HISTORY_LEN),
+        }
+    }
+
+    /// start timing `phase`, ending whichever phase was running before
+    pub fn begin(&mut self, phase: Phase) {
+        self.end();
+        self.running = Some((phase, Instant::now()));
+    }
+
+    /// stop timing the running phase, if any
+    pub fn end(&mut self) {
+        if let Some((phase, started)) = self.running.take() {
+            self.current.add(phase, started.elapsed());
+        }
+    }
+
+    /// time a closure as `phase`
+    pub fn measure<R>(&mut self, phase: Phase, f: impl FnOnce() -> R) -> R {
+        self.begin(phase);
+        let res = f();
+        self.end();
+        res
+    }
+
+    /// commit the current frame to the history and start a new one
+    pub fn end_frame(&mut self) {
+        self.end();
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(self.current);
+        self.current = FrameTimings::default();
+    }
+
+    pub fn last_frame(&self) -> Option<FrameTimings> {
+        self.history.back().copied()
+    }
+
+    /// committed frames, oldest first
+    pub fn history(&self) -> impl Iterator<Item = &FrameTimings> {
+        self.history.iter()
+    }
+
+    /// mean timings over the recorded history
+    pub fn average(&self) -> FrameTimings {
+        let count = self.history.len() as u32;
+        if count == 0 {
+            return FrameTimings::default();
+        }
+
+        let mut sum = FrameTimings::default();
+        for frame in &self.history {
+            sum.emulation += frame.emulation;
+            sum.rendering += frame.rendering;
+            sum.waiting += frame.waiting;
+        }
+        FrameTimings {
+            emulation: sum.emulation / count,
+            rendering: sum.rendering / count,
+            waiting: sum.waiting / count,
+        }
+    }
+
+    /// slowest frame in the recorded history, useful to spot stutters
+    pub fn worst_frame(&self) -> Option<FrameTimings> {
+        self.history.iter().copied().max_by_key(|f| f.total())
+    }
+}
+
+#[cfg(feature = "overlay")]
+impl FrameProfiler {
+    /// draw the history as stacked bars, one column per frame with the newest on
+    /// the right, in a box `HISTORY_LEN` pixels wide and `height` tall with its top
+    /// left corner at (x, y). from the bottom each bar is emulation, rendering then
+    /// waiting, and a frame taking `budget` (e.g. 1/60s) reaches the top, longer
+    /// ones are cut off. `plot(x, y, phase)` is called for every pixel of the box,
+    /// with `None` for the background, so frontends can pick a colour per phase
+    pub fn draw_with(
+        &self,
+        x: usize,
+        y: usize,
+        height: usize,
+        budget: Duration,
+        mut plot: impl FnMut(usize, usize, Option<Phase>),
+    ) {
+        if height == 0 || budget.is_zero() {
+            return;
+        }
+        let rows = |elapsed: Duration| {
+            (elapsed.as_secs_f64() / budget.as_secs_f64() * height as f64).round() as usize
+        };
+        // fewer frames than the history holds leave the left side empty
+        let empty = HISTORY_LEN - self.history.len();
+        let columns = std::iter::repeat_n(None, empty).chain(self.history.iter().map(Some));
+
+        for (col, frame) in columns.enumerate() {
+            // rows from the bottom where each phase's segment ends
+            let mut tops = [
+                (Phase::Emulation, 0),
+                (Phase::Rendering, 0),
+                (Phase::Waiting, 0),
+            ];
+            if let Some(frame) = frame {
+                let mut elapsed = Duration::ZERO;
+                for (phase, top) in tops.iter_mut() {
+                    elapsed += frame.get(*phase);
+                    *top = rows(elapsed);
+                }
+            }
+            for row in 0..height {
+                let from_bottom = height - row;
+                let phase = tops
+                    .iter()
+                    .find(|(_, top)| from_bottom <= *top)
+                    .map(|(phase, _)| *phase);
+                plot(x + col, y + row, phase);
+            }
+        }
+    }
+
+    /// `draw_with` into a row-major buffer `width` pixels wide, clipping at the edges
+    pub fn draw(
+        &self,
+        buf: &mut [Option<Phase>],
+        width: usize,
+        x: usize,
+        y: usize,
+        height: usize,
+        budget: Duration,
+    ) {
+        let buf_height = buf.len() / width.max(1);
+        self.draw_with(x, y, height, budget, |px, py, phase| {
+            if px < width && py < buf_height {
+                buf[py * width + px] = phase;
+            }
+        });
+    }
+}
+
+/// an address range expected to run at most `max_per_frame` instructions a frame
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegionBudget {
+    pub name: String,
+    /// first address of the region
+    pub start: usize,
+    /// last address of the region, inclusive
+    pub end: usize,
+    pub max_per_frame: u32,
+}
+
+/// read the budgets from a project file, one region per line:
+///
+/// ```text
+/// # name      first  last   instructions per frame
+/// budget main 0x200  0x27E  40
+/// ```
+///
+/// addresses and budgets are decimal or `0x` hex, `#` starts a comment
+pub fn parse_budgets(text: &str) -> Result<Vec<RegionBudget>, String> {
+    let mut budgets = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line_no = idx + 1;
+        let words: Vec<&str> = line
+            .split('#')
+            .next()
+            .unwrap_or("")
+            .split_whitespace()
+            .collect();
+        let number = |word: &str| -> Result<usize, String> {
+            let parsed = match word.strip_prefix("0x").or(word.strip_prefix("0X")) {
+                Some(hex) => usize::from_str_radix(hex, 16),
+                None => word.parse(),
+            };
+            parsed.map_err(|_| format!("line {line_no}: {word} is not a number"))
+        };
+        match words.as_slice() {
+            [] => {}
+            ["budget", name, start, end, max_per_frame] => {
+                let (start, end) = (number(start)?, number(end)?);
+                if end < start {
+                    return Err(format!("line {line_no}: {name} ends before it starts"));
+                }
+                let max_per_frame = u32::try_from(number(max_per_frame)?)
+                    .map_err(|_| format!("line {line_no}: {name}'s budget is too large"))?;
+                budgets.push(RegionBudget {
+                    name: name.to_string(),
+                    start,
+                    end,
+                    max_per_frame,
+                });
+            }
+            ["budget", ..] => {
+                return Err(format!(
+                    "line {line_no}: expected budget <name> <first> <last> <instructions>"
+                ));
+            }
+            [key, ..] => return Err(format!("line {line_no}: unknown setting {key}")),
+        }
+    }
+    Ok(budgets)
+}
+
+/// a region that went over its budget in a frame
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BudgetAlert {
+    pub region: String,
+    pub executed: u32,
+    pub max_per_frame: u32,
+}
+
+/// counts instructions executed inside annotated regions and reports the ones
+/// that went over budget.
+///
+/// frontends call `record` with the address of every executed instruction
+/// (e.g. from a hook added with `Chip8::add_hook`) and `end_frame` once per frame
+#[derive(Clone, Debug, Default)]
+pub struct BudgetTracker {
+    budgets: Vec<RegionBudget>,
+    counts: Vec<u32>,
+}
+
+impl BudgetTracker {
+    pub fn new(budgets: Vec<RegionBudget>) -> Self {
+        let counts = vec![0; budgets.len()];
+        Self { budgets, counts }
+    }
+
+    pub fn budgets(&self) -> &[RegionBudget] {
+        &self.budgets
+    }
+
+    /// count an instruction at `addr` against every region containing it
+    pub fn record(&mut self, addr: usize) {
+        for (budget, count) in self.budgets.iter().zip(self.counts.iter_mut()) {
+            if (budget.start..=budget.end).contains(&addr) {
+                *count += 1;
+            }
+        }
+    }
+
+    /// regions over budget this frame, then start counting a new one
+    pub fn end_frame(&mut self) -> Vec<BudgetAlert> {
+        let alerts = self
+            .budgets
+            .iter()
+            .zip(&self.counts)
+            .filter(|(budget, count)| **count > budget.max_per_frame)
+            .map(|(budget, count)| BudgetAlert {
+                region: budget.name.clone(),
+                executed: *count,
+                max_per_frame: budget.max_per_frame,
+            })
+            .collect();
+        self.counts.fill(0);
+        alerts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn budgets_from_a_project_file() {
+        let text =
+            "# game loop\nbudget main 0x200 0x27E 40\n\nbudget draw 640 700 0x10 # sprites\n";
+        let budgets = parse_budgets(text).unwrap();
+        assert_eq!(
+            budgets,
+            [
+                RegionBudget {
+                    name: "main".to_string(),
+                    start: 0x200,
+                    end: 0x27E,
+                    max_per_frame: 40,
+                },
+                RegionBudget {
+                    name: "draw".to_string(),
+                    start: 640,
+                    end: 700,
+                    max_per_frame: 16,
+                },
+            ]
+        );
+
+        let mut tracker = BudgetTracker::new(budgets);
+        for addr in [0x200, 0x202, 640] {
+            for _ in 0..20 {
+                tracker.record(addr);
+            }
+        }
+        assert_eq!(
+            tracker.end_frame(),
+            [BudgetAlert {
+                region: "draw".to_string(),
+                executed: 20,
+                max_per_frame: 16,
+            }]
+        );
+        assert_eq!(tracker.end_frame(), []);
+
+        assert_eq!(
+            parse_budgets("budget main 0x200 40"),
+            Err("line 1: expected budget <name> <first> <last> <instructions>".to_string())
+        );
+        assert_eq!(
+            parse_budgets("\nbudgit main 0x200 0x27E 40"),
+            Err("line 2: unknown setting budgit".to_string())
+        );
+    }
+
+    #[cfg(feature = "overlay")]
+    #[test]
+    fn graph_stacks_phases_from_the_bottom() {
+        let mut profiler = FrameProfiler::new();
+        profiler.history.push_back(FrameTimings {
+            emulation: Duration::from_millis(2),
+            rendering: Duration::from_millis(4),
+            waiting: Duration::from_millis(2),
+        });
+        let mut buf = vec![None; HISTORY_LEN * 10];
+        profiler.draw(&mut buf, HISTORY_LEN, 0, 0, 10, Duration::from_millis(10));
+
+        let newest: Vec<_> = (0..10)
+            .map(|row| buf[row * HISTORY_LEN + HISTORY_LEN - 1])
+            .collect();
+        let (e, r, w) = (
+            Some(Phase::Emulation),
+            Some(Phase::Rendering),
+            Some(Phase::Waiting),
+        );
+        assert_eq!(newest, [None, None, w, w, r, r, r, r, e, e]);
+        // the columns for frames not recorded yet stay empty
+        assert!(
+            buf.chunks(HISTORY_LEN)
+                .all(|row| row[..HISTORY_LEN - 1].iter().all(Option::is_none))
+        );
+    }
+}
@@ -0,0 +1,48 @@
+//! run-ahead: show the screen a few frames early to hide input latency
+
+use crate::emulator::core::Chip8;
+use crate::emulator::error::Chip8Error;
+use crate::emulator::state::{ProgramState, Screen, TimerState};
+
+/// runs the real machine one frame at a time and a throwaway fork of it
+/// `frames` further, with the same inputs held down.
+///
+/// the fork's screen is what gets displayed, so a key press shows up `frames`
+/// frames sooner. nothing needs rolling back when the input changes, the fork is
+/// rebuilt from the real machine every frame. sound and halting follow the real
+/// machine
+pub struct RunAhead {
+    frames: u32,
+}
+
+impl RunAhead {
+    pub fn new(frames: u32) -> Self {
+        Self { frames }
+    }
+
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// advance `chip8` one frame of `cycles` instructions, returns its state
+    /// along with the screen to display. an error in the fork just ends the run-ahead early
+    pub fn step_frame(
+        &self,
+        chip8: &mut Chip8,
+        cycles: u32,
+    ) -> Result<(ProgramState, TimerState, Screen), Chip8Error> {
+        let (state, timers) = chip8.step_frame(cycles)?;
+        if self.frames == 0 || state != ProgramState::Running {
+            return Ok((state, timers, chip8.get_screen().clone()));
+        }
+
+        let mut fork = chip8.clone();
+        for _ in 0..self.frames {
+            match fork.step_frame(cycles) {
+                Ok((ProgramState::Running, _)) => {}
+                _ => break,
+            }
+        }
+        Ok((state, timers, fork.get_screen().clone()))
+    }
+}
@@ -0,0 +1,118 @@
+//! search for input sequences that drive a rom to a goal state.
+//!
+//! built on cloning: every candidate is a forked machine, so the rom runs exactly as
+//! it would live. forks continue the machine's `CXNN` sequence, so a solution replays
+//! the same way from the starting machine or a copy of it
+
+use crate::emulator::core::{Chip8, NUM_KEYS};
+use crate::emulator::state::ProgramState;
+
+/// key held down for one search step, `None` for no key
+pub type Input = Option<u8>;
+
+#[derive(Clone, Copy, Debug)]
+pub struct SearchConfig {
+    /// frames each input is held for
+    pub frames_per_step: u32,
+    /// instructions executed per frame
+    pub cycles_per_frame: u32,
+    /// give up after this many steps
+    pub max_depth: usize,
+    /// candidates kept after every step, `usize::MAX` makes this a breadth-first search
+    pub beam_width: usize,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            frames_per_step: 10,
+            cycles_per_frame: 10,
+            max_depth: 50,
+            beam_width: 32,
+        }
+    }
+}
+
+struct Candidate {
+    machine: Chip8,
+    inputs: Vec<Input>,
+    score: i64,
+}
+
+fn all_inputs() -> impl Iterator<Item = Input> {
+    std::iter::once(None).chain((0..NUM_KEYS as u8).map(Some))
+}
+
+/// run one step of `input` on `machine`, returns false if the machine halted or faulted
+fn apply(machine: &mut Chip8, input: Input, config: &SearchConfig) -> bool {
+    if let Some(key) = input {
+        machine.set_key(key, true);
+    }
+    let mut running = true;
+    for _ in 0..config.frames_per_step {
+        let state = machine.step_frame(config.cycles_per_frame);
+        if !matches!(state, Ok((ProgramState::Running | ProgramState::Paused, _))) {
+            running = false;
+            break;
+        }
+    }
+    if let Some(key) = input {
+        machine.set_key(key, false);
+    }
+    running
+}
+
+/// beam search over key inputs starting from `start`.
+///
+/// `score` ranks candidates (higher is better), `goal` ends the search.
+/// returns the inputs that reach the goal, one per step
+pub fn search(
+    start: &Chip8,
+    config: &SearchConfig,
+    score: impl Fn(&Chip8) -> i64,
+    goal: impl Fn(&Chip8) -> bool,
+) -> Option<Vec<Input>> {
+    if goal(start) {
+        return Some(Vec::new());
+    }
+
+    let mut beam = vec![Candidate {
+        machine: start.clone(),
+        inputs: Vec::new(),
+        score: score(start),
+    }];
+
+    for _ in 0..config.max_depth {
+        let mut next = Vec::new();
+        for candidate in &beam {
+            for input in all_inputs() {
+                let mut machine = candidate.machine.clone();
+                let running = apply(&mut machine, input, config);
+
+                let mut inputs = candidate.inputs.clone();
+                inputs.push(input);
+                if goal(&machine) {
+                    return Some(inputs);
+                }
+                if running {
+                    let score = score(&machine);
+                    next.push(Candidate {
+                        machine,
+                        inputs,
+                        score,
+                    });
+                }
+            }
+        }
+
+        if next.is_empty() {
+            return None;
+        }
+        // stable sort, so among equal scores the earlier (shorter, no-key first) inputs win
+        next.sort_by_key(|candidate| std::cmp::Reverse(candidate.score));
+        next.truncate(config.beam_width);
+        beam = next;
+    }
+
+    None
+}
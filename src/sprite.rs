@@ -0,0 +1,321 @@
+//! converting between images and sprite data, for `chip8-emu sprite` and `chip8-emu extract`
+
+use std::io::Cursor;
+
+/// the first bytes of every png file
+pub const PNG_SIGNATURE: &[u8] = &[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+/// widest sprite `DXY0` can draw
+pub const MAX_SPRITE_WIDTH: usize = 16;
+
+/// a decoded image, one color index per pixel, row by row
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+/// parse a plain (ascii) netpbm image: `P1` bitmaps become a single plane,
+/// `P2` graymaps with a max value of up to 3 give two plane color indices
+pub fn parse_netpbm(text: &str) -> Result<Image, String> {
+    let mut tokens = text
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(str::split_whitespace);
+
+    let magic = tokens.next().ok_or("empty image")?;
+    let mut number = |what: &str| -> Result<usize, String> {
+        tokens
+            .next()
+            .ok_or(format!("missing {what}"))?
+            .parse()
+            .map_err(|_| format!("bad {what}"))
+    };
+
+    let width = number("width")?;
+    let height = number("height")?;
+    let pixels = match magic {
+        "P1" => (0..width * height)
+            .map(|_| number("pixel").map(|px| u8::from(px != 0)))
+            .collect::<Result<_, _>>()?,
+        "P2" => {
+            let max = number("max value")?;
+            if max > 3 {
+                return Err(format!("max value {max} has more than 2 planes of colors"));
+            }
+            // graymaps are white at max, sprites are lit at a high color index
+            (0..width * height)
+                .map(|_| number("pixel").map(|px| (max - px.min(max)) as u8))
+                .collect::<Result<_, _>>()?
+        }
+        _ => return Err(format!("{magic} is not a plain pbm (P1) or pgm (P2)")),
+    };
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// decode a png. palette images use the palette index as the color index, so
+/// indices 0 to 3 cover both planes. anything else is turned to gray, darker is a
+/// higher color index in 4 steps, and an image with only black and white becomes
+/// a single plane. transparent pixels are unlit
+pub fn parse_png(bytes: &[u8]) -> Result<Image, String> {
+    let decoder = png::Decoder::new(Cursor::new(bytes));
+    let mut reader = decoder.read_info().map_err(|err| err.to_string())?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let frame = reader.next_frame(&mut buf).map_err(|err| err.to_string())?;
+    let (width, height) = (frame.width as usize, frame.height as usize);
+    let depth = frame.bit_depth as usize;
+
+    // every sample of a row, unpacked from 1, 2, 4, 8 or 16 bits
+    let samples = |row: &[u8]| -> Vec<u32> {
+        match depth {
+            16 => row
+                .chunks(2)
+                .map(|pair| u32::from(u16::from_be_bytes([pair[0], pair[1]])))
+                .collect(),
+            8 => row.iter().map(|byte| u32::from(*byte)).collect(),
+            _ => row
+                .iter()
+                .flat_map(|byte| {
+                    (0..8 / depth).map(move |idx| byte << (idx * depth) >> (8 - depth))
+                })
+                .map(u32::from)
+                .collect(),
+        }
+    };
+    let max = (1u32 << depth) - 1;
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in buf[..frame.buffer_size()].chunks(frame.line_size) {
+        let row = samples(row);
+        for x in 0..width {
+            let px = match frame.color_type {
+                png::ColorType::Indexed => {
+                    let idx = row[x];
+                    if idx > 3 {
+                        return Err(format!(
+                            "palette index {idx} has more than 2 planes of colors"
+                        ));
+                    }
+                    idx as u8
+                }
+                png::ColorType::Grayscale => gray_level(row[x], max),
+                png::ColorType::GrayscaleAlpha => {
+                    transparent_or(row[x * 2 + 1], max, gray_level(row[x * 2], max))
+                }
+                png::ColorType::Rgb => gray_level(luma(&row[x * 3..x * 3 + 3]), max),
+                png::ColorType::Rgba => transparent_or(
+                    row[x * 4 + 3],
+                    max,
+                    gray_level(luma(&row[x * 4..x * 4 + 3]), max),
+                ),
+            };
+            pixels.push(px);
+        }
+    }
+
+    // black and white only, keep it to one plane like a pbm
+    if pixels.iter().all(|px| *px == 0 || *px == 3) && frame.color_type != png::ColorType::Indexed {
+        pixels.iter_mut().for_each(|px| *px &= 1);
+    }
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// 0 for white up to 3 for black
+fn gray_level(value: u32, max: u32) -> u8 {
+    ((max - value) * 3 + max / 2).checked_div(max).unwrap_or(0) as u8
+}
+
+fn luma(rgb: &[u32]) -> u32 {
+    (rgb[0] * 299 + rgb[1] * 587 + rgb[2] * 114) / 1000
+}
+
+fn transparent_or(alpha: u32, max: u32, px: u8) -> u8 {
+    if alpha * 2 < max { 0 } else { px }
+}
+
+/// sprite bytes for `image`, 1 byte per row up to 8 pixels wide and 2 up to 16.
+/// when any pixel uses the second plane the plane 2 sprite follows the plane 1 one,
+/// the layout xo-chip `DXYN` reads with both planes selected
+pub fn encode(image: &Image) -> Result<Vec<u8>, String> {
+    if image.width == 0 || image.width > MAX_SPRITE_WIDTH {
+        return Err(format!(
+            "sprites are 1 to {MAX_SPRITE_WIDTH} pixels wide, the image is {}",
+            image.width
+        ));
+    }
+    let bytes_per_row = image.width.div_ceil(8);
+    let planes = if image.pixels.iter().any(|px| px & 0b10 != 0) {
+        2
+    } else {
+        1
+    };
+
+    let mut bytes = Vec::new();
+    for plane in 0..planes {
+        for row in image.pixels.chunks(image.width) {
+            for byte_idx in 0..bytes_per_row {
+                let mut byte = 0u8;
+                for bit in 0..8 {
+                    let lit = row
+                        .get(byte_idx * 8 + bit)
+                        .is_some_and(|px| px & (1 << plane) != 0);
+                    if lit {
+                        byte |= 0b1000_0000 >> bit;
+                    }
+                }
+                bytes.push(byte);
+            }
+        }
+    }
+    Ok(bytes)
+}
+
+/// a single plane sprite as an image, `width` pixels per row (8 or 16)
+pub fn decode(bytes: &[u8], width: usize) -> Image {
+    let bytes_per_row = width.div_ceil(8);
+    let pixels: Vec<u8> = bytes
+        .chunks(bytes_per_row)
+        .flat_map(|row| (0..width).map(move |x| row[x / 8] >> (7 - x % 8) & 1))
+        .collect();
+    Image {
+        width,
+        height: pixels.len() / width,
+        pixels,
+    }
+}
+
+/// encode `image` as a grayscale png, the reverse of `parse_png`: 1 bit deep and
+/// lit pixels black for a single plane, 2 bits deep when the second plane is used
+pub fn write_png(image: &Image) -> Result<Vec<u8>, String> {
+    let (depth, bits) = if image.pixels.iter().any(|px| px & 0b10 != 0) {
+        (png::BitDepth::Two, 2)
+    } else {
+        (png::BitDepth::One, 1)
+    };
+    let max = (1u8 << bits) - 1;
+    let mut data = Vec::new();
+    for row in image.pixels.chunks(image.width) {
+        for chunk in row.chunks(8 / bits) {
+            let mut byte = 0u8;
+            for (idx, px) in chunk.iter().enumerate() {
+                // white at max, like the graymaps `parse_netpbm` reads
+                let sample = max - (px & max);
+                byte |= sample << (8 - bits * (idx + 1));
+            }
+            data.push(byte);
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, image.width as u32, image.height as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(depth);
+    let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+    writer
+        .write_image_data(&data)
+        .map_err(|err| err.to_string())?;
+    writer.finish().map_err(|err| err.to_string())?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a png of `samples`, already packed at `depth` bits
+    fn png(
+        width: u32,
+        height: u32,
+        color: png::ColorType,
+        depth: png::BitDepth,
+        samples: &[u8],
+    ) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, width, height);
+        encoder.set_color(color);
+        encoder.set_depth(depth);
+        if color == png::ColorType::Indexed {
+            encoder.set_palette(vec![255, 255, 255, 170, 170, 170, 85, 85, 85, 0, 0, 0]);
+        }
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(samples).unwrap();
+        writer.finish().unwrap();
+        out
+    }
+
+    #[test]
+    fn one_bit_png_is_one_plane() {
+        // 0 is black, the row is padded to a byte
+        let bytes = png(
+            4,
+            2,
+            png::ColorType::Grayscale,
+            png::BitDepth::One,
+            &[0b0110_0000, 0b1001_0000],
+        );
+        let image = parse_png(&bytes).unwrap();
+        assert_eq!(image.pixels, [1, 0, 0, 1, 0, 1, 1, 0]);
+        assert_eq!(encode(&image).unwrap(), [0b1001_0000, 0b0110_0000]);
+    }
+
+    #[test]
+    fn gray_png_uses_both_planes() {
+        let bytes = png(
+            4,
+            1,
+            png::ColorType::Grayscale,
+            png::BitDepth::Eight,
+            &[255, 170, 85, 0],
+        );
+        let image = parse_png(&bytes).unwrap();
+        assert_eq!(image.pixels, [0, 1, 2, 3]);
+        // plane 1 then plane 2
+        assert_eq!(encode(&image).unwrap(), [0b0101_0000, 0b0011_0000]);
+    }
+
+    #[test]
+    fn black_and_white_gray_png_is_one_plane() {
+        let bytes = png(
+            2,
+            1,
+            png::ColorType::Grayscale,
+            png::BitDepth::Eight,
+            &[0, 255],
+        );
+        assert_eq!(parse_png(&bytes).unwrap().pixels, [1, 0]);
+    }
+
+    #[test]
+    fn palette_png_uses_the_index() {
+        let bytes = png(
+            4,
+            1,
+            png::ColorType::Indexed,
+            png::BitDepth::Two,
+            &[0b0001_1011],
+        );
+        assert_eq!(parse_png(&bytes).unwrap().pixels, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn written_png_reads_back() {
+        let one_plane = decode(&[0b1010_0000, 0b0101_1111], 8);
+        assert_eq!(parse_png(&write_png(&one_plane).unwrap()), Ok(one_plane));
+
+        let two_planes = Image {
+            width: 4,
+            height: 1,
+            pixels: vec![0, 1, 2, 3],
+        };
+        assert_eq!(parse_png(&write_png(&two_planes).unwrap()), Ok(two_planes));
+    }
+}
@@ -0,0 +1,87 @@
+//! synthetic roms that each hammer one part of the interpreter, for benchmarks
+//! and as seeds for fuzzing. all of them loop forever, run them for a fixed
+//! number of frames
+
+use crate::emulator::core::START_ADDR;
+
+/// the deepest `deep_calls` goes, the interpreter's stack holds 16 return addresses
+pub const MAX_CALL_DEPTH: usize = 16;
+
+/// the kinds of stress rom, by name
+pub const KINDS: &[&str] = &["draw", "calls", "memory", "resolution"];
+
+/// build the stress rom called `kind`, see `KINDS`
+pub fn generate(kind: &str) -> Option<Vec<u8>> {
+    match kind {
+        "draw" => Some(draw_throughput()),
+        "calls" => Some(deep_calls(MAX_CALL_DEPTH)),
+        "memory" => Some(random_memory_walk()),
+        "resolution" => Some(resolution_switching()),
+        _ => None,
+    }
+}
+
+/// mostly `DXYN`: a 5 row font sprite drawn over and over while the
+/// coordinates drift, so it wraps and collides constantly. every 256 draws
+/// the screen is cleared
+pub fn draw_throughput() -> Vec<u8> {
+    assemble(&[
+        0xA000, // 200: I = font sprite for 0
+        0xD015, // 202: draw 8x5 at (V0, V1)
+        0x7003, // 204: V0 += 3
+        0x7102, // 206: V1 += 2
+        0x7201, // 208: V2 += 1
+        0x3200, // 20A: skip the jump when V2 wrapped to 0
+        0x1202, // 20C: jump 202
+        0x00E0, // 20E: clear the screen
+        0x1202, // 210: jump 202
+    ])
+}
+
+/// a chain of `depth` nested calls (capped at `MAX_CALL_DEPTH`), each subroutine
+/// calls the next one and returns, then the chain starts over
+pub fn deep_calls(depth: usize) -> Vec<u8> {
+    let depth = depth.clamp(1, MAX_CALL_DEPTH);
+    // main is 2 instructions, each subroutine is a call and a return, the last one just returns
+    let sub_addr = |idx: usize| (START_ADDR + 4 + idx * 4) as u16;
+
+    let mut words = vec![0x2000 | sub_addr(0), 0x1000 | START_ADDR as u16];
+    for idx in 0..depth - 1 {
+        words.push(0x2000 | sub_addr(idx + 1));
+        words.push(0x00EE);
+    }
+    words.push(0x00EE);
+    assemble(&words)
+}
+
+/// reads and writes at random offsets into a 256 byte window, every iteration
+/// touches a different place
+pub fn random_memory_walk() -> Vec<u8> {
+    assemble(&[
+        0xC0FF, // 200: V0 = random byte
+        0xA400, // 202: I = 400
+        0xF01E, // 204: I += V0
+        0xF165, // 206: load V0, V1 from I
+        0x7101, // 208: V1 += 1
+        0xF155, // 20A: store V0, V1 at I
+        0x1200, // 20C: jump 200
+    ])
+}
+
+/// schip: flip between lores and hires every few instructions with a sprite on
+/// screen, which rescales or clears the whole display each time
+pub fn resolution_switching() -> Vec<u8> {
+    assemble(&[
+        0xA000, // 200: I = font sprite for 0
+        0x00FF, // 202: hires
+        0xD015, // 204: draw 8x5 at (V0, V1)
+        0x00FE, // 206: lores
+        0xD015, // 208: draw 8x5 at (V0, V1)
+        0x7001, // 20A: V0 += 1
+        0x1202, // 20C: jump 202
+    ])
+}
+
+fn assemble(words: &[u16]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_be_bytes()).collect()
+}
@@ -0,0 +1,134 @@
+//! tiny 3x5 bitmap font for overlays (osd, debug views, menus).
+//!
+//! drawing goes through a `plot(x, y)` callback so the same glyphs can be
+//! used for boolean buffers, rgba buffers or terminal cells.
+
+pub const GLYPH_WIDTH: usize = 3;
+pub const GLYPH_HEIGHT: usize = 5;
+/// horizontal distance between the start of two consecutive glyphs
+pub const GLYPH_ADVANCE: usize = GLYPH_WIDTH + 1;
+/// vertical distance between two lines of text
+pub const LINE_HEIGHT: usize = GLYPH_HEIGHT + 1;
+
+const FIRST_CHAR: u8 = b' ';
+const LAST_CHAR: u8 = b'_';
+
+/// glyphs for ascii 0x20..=0x5F, each row is 3 bits with the msb on the left
+const GLYPHS: [[u8; GLYPH_HEIGHT]; (LAST_CHAR - FIRST_CHAR + 1) as usize] = [
+    [0b000, 0b000, 0b000, 0b000, 0b000], // space
+    [0b010, 0b010, 0b010, 0b000, 0b010], // !
+    [0b101, 0b101, 0b000, 0b000, 0b000], // "
+    [0b101, 0b111, 0b101, 0b111, 0b101], // #
+    [0b011, 0b110, 0b010, 0b011, 0b110], // $
+    [0b101, 0b001, 0b010, 0b100, 0b101], // %
+    [0b010, 0b101, 0b010, 0b101, 0b011], // &
+    [0b010, 0b010, 0b000, 0b000, 0b000], // '
+    [0b001, 0b010, 0b010, 0b010, 0b001], // (
+    [0b100, 0b010, 0b010, 0b010, 0b100], // )
+    [0b000, 0b101, 0b010, 0b101, 0b000], // *
+    [0b000, 0b010, 0b111, 0b010, 0b000], // +
+    [0b000, 0b000, 0b000, 0b010, 0b100], // ,
+    [0b000, 0b000, 0b111, 0b000, 0b000], // -
+    [0b000, 0b000, 0b000, 0b000, 0b010], // .
+    [0b001, 0b001, 0b010, 0b100, 0b100], // /
+    [0b111, 0b101, 0b101, 0b101, 0b111], // 0
+    [0b010, 0b110, 0b010, 0b010, 0b111], // 1
+    [0b111, 0b001, 0b111, 0b100, 0b111], // 2
+    [0b111, 0b001, 0b111, 0b001, 0b111], // 3
+    [0b101, 0b101, 0b111, 0b001, 0b001], // 4
+    [0b111, 0b100, 0b111, 0b001, 0b111], // 5
+    [0b111, 0b100, 0b111, 0b101, 0b111], // 6
+    [0b111, 0b001, 0b001, 0b010, 0b010], // 7
+    [0b111, 0b101, 0b111, 0b101, 0b111], // 8
+    [0b111, 0b101, 0b111, 0b001, 0b111], // 9
+    [0b000, 0b010, 0b000, 0b010, 0b000], // :
+    [0b000, 0b010, 0b000, 0b010, 0b100], // ;
+    [0b001, 0b010, 0b100, 0b010, 0b001], // <
+    [0b000, 0b111, 0b000, 0b111, 0b000], // =
+    [0b100, 0b010, 0b001, 0b010, 0b100], // >
+    [0b111, 0b001, 0b011, 0b000, 0b010], // ?
+    [0b111, 0b101, 0b111, 0b100, 0b111], // @
+    [0b010, 0b101, 0b111, 0b101, 0b101], // A
+    [0b110, 0b101, 0b110, 0b101, 0b110], // B
+    [0b011, 0b100, 0b100, 0b100, 0b011], // C
+    [0b110, 0b101, 0b101, 0b101, 0b110], // D
+    [0b111, 0b100, 0b110, 0b100, 0b111], // E
+    [0b111, 0b100, 0b110, 0b100, 0b100], // F
+    [0b011, 0b100, 0b101, 0b101, 0b011], // G
+    [0b101, 0b101, 0b111, 0b101, 0b101], // H
+    [0b111, 0b010, 0b010, 0b010, 0b111], // I
+    [0b001, 0b001, 0b001, 0b101, 0b010], // J
+    [0b101, 0b101, 0b110, 0b101, 0b101], // K
+    [0b100, 0b100, 0b100, 0b100, 0b111], // L
+    [0b101, 0b111, 0b111, 0b101, 0b101], // M
+    [0b110, 0b101, 0b101, 0b101, 0b101], // N
+    [0b010, 0b101, 0b101, 0b101, 0b010], // O
+    [0b110, 0b101, 0b110, 0b100, 0b100], // P
+    [0b010, 0b101, 0b101, 0b110, 0b011], // Q
+    [0b110, 0b101, 0b110, 0b101, 0b101], // R
+    [0b011, 0b100, 0b010, 0b001, 0b110], // S
+    [0b111, 0b010, 0b010, 0b010, 0b010], // T
+    [0b101, 0b101, 0b101, 0b101, 0b111], // U
+    [0b101, 0b101, 0b101, 0b101, 0b010], // V
+    [0b101, 0b101, 0b111, 0b111, 0b101], // W
+    [0b101, 0b101, 0b010, 0b101, 0b101], // X
+    [0b101, 0b101, 0b010, 0b010, 0b010], // Y
+    [0b111, 0b001, 0b010, 0b100, 0b111], // Z
+    [0b110, 0b100, 0b100, 0b100, 0b110], // [
+    [0b100, 0b100, 0b010, 0b001, 0b001], // \\
+    [0b011, 0b001, 0b001, 0b001, 0b011], // ]
+    [0b010, 0b101, 0b000, 0b000, 0b000], // ^
+    [0b000, 0b000, 0b000, 0b000, 0b111], // _
+];
+
+fn glyph(c: char) -> &'static [u8; GLYPH_HEIGHT] {
+    let c = c.to_ascii_uppercase();
+    let code = if c.is_ascii() { c as u8 } else { b'?' };
+    let code = if (FIRST_CHAR..=LAST_CHAR).contains(&code) {
+        code
+    } else {
+        b'?'
+    };
+    &GLYPHS[usize::from(code - FIRST_CHAR)]
+}
+
+/// width in pixels of the widest line of `text`
+pub fn text_width(text: &str) -> usize {
+    text.lines()
+        .map(|line| (line.chars().count() * GLYPH_ADVANCE).saturating_sub(1))
+        .max()
+        .unwrap_or(0)
+}
+
+/// height in pixels of `text`
+pub fn text_height(text: &str) -> usize {
+    (text.lines().count() * LINE_HEIGHT).saturating_sub(1)
+}
+
+/// draw `text` with its top left corner at (x, y), calling `plot` for every lit pixel.
+/// lowercase is drawn as uppercase, anything else outside the font as `?`
+pub fn draw_text_with(text: &str, x: usize, y: usize, mut plot: impl FnMut(usize, usize)) {
+    for (line_idx, line) in text.lines().enumerate() {
+        let top = y + line_idx * LINE_HEIGHT;
+        for (char_idx, c) in line.chars().enumerate() {
+            let left = x + char_idx * GLYPH_ADVANCE;
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for col in 0..GLYPH_WIDTH {
+                    if bits & (0b100 >> col) != 0 {
+                        plot(left + col, top + row);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// draw `text` into a row-major boolean buffer `width` pixels wide, clipping at the edges
+pub fn draw_text(buf: &mut [bool], width: usize, x: usize, y: usize, text: &str) {
+    let height = buf.len() / width.max(1);
+    draw_text_with(text, x, y, |px, py| {
+        if px < width && py < height {
+            buf[py * width + px] = true;
+        }
+    });
+}
@@ -0,0 +1,89 @@
+//! per-frame record of memory changes, for scrubbing through when bytes changed
+
+use crate::emulator::core::Chip8;
+
+/// a byte that changed during a frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ByteChange {
+    pub addr: usize,
+    pub old: u8,
+    pub new: u8,
+}
+
+/// stores only the bytes that changed each frame, so recording a long run
+/// costs memory proportional to how much the rom writes, not to its length
+pub struct MemoryTimeline {
+    /// memory as of the last recorded frame
+    last: Vec<u8>,
+    /// changes per frame, oldest first
+    frames: Vec<Vec<ByteChange>>,
+    /// memory as of the first recorded frame
+    initial: Vec<u8>,
+}
+
+impl MemoryTimeline {
+    /// start recording from the machine's current memory
+    pub fn new(chip8: &Chip8) -> Self {
+        let initial = chip8.memory().as_slice().to_vec();
+        Self {
+            last: initial.clone(),
+            frames: Vec::new(),
+            initial,
+        }
+    }
+
+    /// call once per frame, after `tick_timers`
+    pub fn record_frame(&mut self, chip8: &Chip8) {
+        let current = chip8.memory().as_slice();
+        let changes = self
+            .last
+            .iter_mut()
+            .zip(current)
+            .enumerate()
+            .filter(|(_, (old, new))| *old != *new)
+            .map(|(addr, (old, new))| {
+                let change = ByteChange {
+                    addr,
+                    old: *old,
+                    new: *new,
+                };
+                *old = *new;
+                change
+            })
+            .collect();
+        self.frames.push(changes);
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// bytes changed during frame `frame`
+    pub fn changes(&self, frame: usize) -> &[ByteChange] {
+        self.frames.get(frame).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// frames during which `addr` changed, along with the change
+    pub fn history(&self, addr: usize) -> impl Iterator<Item = (usize, ByteChange)> + '_ {
+        self.frames
+            .iter()
+            .enumerate()
+            .flat_map(move |(frame, changes)| {
+                changes
+                    .iter()
+                    .filter(move |change| change.addr == addr)
+                    .map(move |change| (frame, *change))
+            })
+    }
+
+    /// reconstruct memory as it was at the end of frame `frame`
+    pub fn memory_at(&self, frame: usize) -> Vec<u8> {
+        let mut memory = self.initial.clone();
+        for changes in self.frames.iter().take(frame + 1) {
+            for change in changes {
+                memory[change.addr] = change.new;
+            }
+        }
+        memory
+    }
+}
@@ -0,0 +1,124 @@
+//! scrolling graph of the delay and sound timers, for spotting when a rom sets
+//! them and how frontend pacing affects their countdown
+
+use crate::emulator::core::Chip8;
+use crate::text;
+use std::collections::VecDeque;
+
+/// samples kept by `TimerHistory::default`, 3 seconds at 60Hz
+pub const DEFAULT_TIMER_HISTORY: usize = 180;
+
+/// which timer to graph
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timer {
+    Delay,
+    Sound,
+}
+
+impl Timer {
+    fn label(self) -> &'static str {
+        match self {
+            Timer::Delay => "DT",
+            Timer::Sound => "ST",
+        }
+    }
+}
+
+/// the timer values as of the end of each of the last `capacity` frames
+#[derive(Clone, Debug)]
+pub struct TimerHistory {
+    /// (delay, sound) per frame, oldest first
+    samples: VecDeque<(u8, u8)>,
+    capacity: usize,
+}
+
+impl Default for TimerHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_TIMER_HISTORY)
+    }
+}
+
+impl TimerHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// call once per frame, after the timers ticked
+    pub fn record(&mut self, chip8: &Chip8) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples
+            .push_back((chip8.delay_timer(), chip8.sound_timer()));
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// recorded values of `timer`, oldest first
+    pub fn values(&self, timer: Timer) -> impl Iterator<Item = u8> + '_ {
+        self.samples.iter().map(move |(delay, sound)| match timer {
+            Timer::Delay => *delay,
+            Timer::Sound => *sound,
+        })
+    }
+
+    /// draw `timer` as one column per frame, newest on the right, in a box `capacity`
+    /// pixels wide and `height` tall with its top left corner at (x, y). the graph is
+    /// scaled so the largest recorded value reaches the top.
+    /// `plot(x, y, lit)` is called for every pixel of the box
+    pub fn draw_with(
+        &self,
+        timer: Timer,
+        x: usize,
+        y: usize,
+        height: usize,
+        mut plot: impl FnMut(usize, usize, bool),
+    ) {
+        if height == 0 {
+            return;
+        }
+        let max = self.values(timer).max().unwrap_or(0).max(1);
+        // fewer samples than capacity leave the left side empty
+        let empty = self.capacity - self.samples.len();
+        let columns = std::iter::repeat_n(0, empty).chain(self.values(timer));
+
+        for (col, value) in columns.enumerate() {
+            let bar = (usize::from(value) * height).div_ceil(usize::from(max));
+            for row in 0..height {
+                plot(x + col, y + row, height - row <= bar);
+            }
+        }
+        // the label goes on top, in the top left corner where bars are rarely that tall
+        text::draw_text_with(timer.label(), x + 1, y + 1, |px, py| plot(px, py, true));
+    }
+
+    /// `draw_with` into a row-major boolean buffer `width` pixels wide, clipping at the edges
+    pub fn draw(
+        &self,
+        buf: &mut [bool],
+        width: usize,
+        timer: Timer,
+        x: usize,
+        y: usize,
+        height: usize,
+    ) {
+        let buf_height = buf.len() / width.max(1);
+        self.draw_with(timer, x, y, height, |px, py, lit| {
+            if px < width && py < buf_height {
+                buf[py * width + px] = lit;
+            }
+        });
+    }
+}
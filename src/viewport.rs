@@ -0,0 +1,103 @@
+//! mapping between window coordinates and chip8 pixels, for mouse input
+
+/// where the chip8 screen sits inside a window: scaled uniformly to fit,
+/// centered, with letterbox bars on the sides that don't fill up
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    screen_width: usize,
+    screen_height: usize,
+    scale: f32,
+    offset_x: f32,
+    offset_y: f32,
+}
+
+impl Viewport {
+    /// fit a `screen_width`x`screen_height` screen (see `Screen::width`) into the window
+    pub fn fit(
+        window_width: f32,
+        window_height: f32,
+        screen_width: usize,
+        screen_height: usize,
+    ) -> Self {
+        let scale = (window_width / screen_width as f32).min(window_height / screen_height as f32);
+        Self::with_scale(
+            window_width,
+            window_height,
+            screen_width,
+            screen_height,
+            scale,
+        )
+    }
+
+    /// like `fit`, but only whole multiples of the pixel size so pixels stay square
+    /// and equally sized. falls back to `fit` when the window is smaller than the screen
+    pub fn fit_integer(
+        window_width: f32,
+        window_height: f32,
+        screen_width: usize,
+        screen_height: usize,
+    ) -> Self {
+        let fitted = Self::fit(window_width, window_height, screen_width, screen_height);
+        if fitted.scale < 1.0 {
+            return fitted;
+        }
+        let scale = fitted.scale.floor();
+        Self::with_scale(
+            window_width,
+            window_height,
+            screen_width,
+            screen_height,
+            scale,
+        )
+    }
+
+    fn with_scale(
+        window_width: f32,
+        window_height: f32,
+        screen_width: usize,
+        screen_height: usize,
+        scale: f32,
+    ) -> Self {
+        Self {
+            screen_width,
+            screen_height,
+            scale,
+            offset_x: (window_width - screen_width as f32 * scale) / 2.0,
+            offset_y: (window_height - screen_height as f32 * scale) / 2.0,
+        }
+    }
+
+    /// size of one chip8 pixel in window units
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// top left corner of the screen in the window
+    pub fn origin(&self) -> (f32, f32) {
+        (self.offset_x, self.offset_y)
+    }
+
+    /// the chip8 pixel under window coordinate (x, y), `None` on the letterbox bars
+    pub fn to_pixel(&self, x: f32, y: f32) -> Option<(usize, usize)> {
+        let px = ((x - self.offset_x) / self.scale).floor();
+        let py = ((y - self.offset_y) / self.scale).floor();
+        if px < 0.0 || py < 0.0 {
+            return None;
+        }
+        let (px, py) = (px as usize, py as usize);
+        if px >= self.screen_width || py >= self.screen_height {
+            return None;
+        }
+        Some((px, py))
+    }
+
+    /// window rectangle `(x, y, width, height)` covered by chip8 pixel (x, y)
+    pub fn pixel_rect(&self, x: usize, y: usize) -> (f32, f32, f32, f32) {
+        (
+            self.offset_x + x as f32 * self.scale,
+            self.offset_y + y as f32 * self.scale,
+            self.scale,
+            self.scale,
+        )
+    }
+}